@@ -0,0 +1,70 @@
+//! Área de transferência do sistema
+//! Clipboard e seleção primária via arboard
+
+use crate::term::ClipboardKind;
+
+/// Acesso ao clipboard; sem servidor gráfico as operações viram no-op
+pub struct Clipboard {
+    inner: Option<arboard::Clipboard>,
+}
+
+impl Clipboard {
+    pub fn new() -> Self {
+        let inner = arboard::Clipboard::new()
+            .map_err(|e| log::warn!("Clipboard indisponível: {}", e))
+            .ok();
+        Self { inner }
+    }
+
+    /// Substitui o conteúdo de uma seleção
+    pub fn store(&mut self, kind: ClipboardKind, text: &str) {
+        let Some(clipboard) = self.inner.as_mut() else {
+            return;
+        };
+
+        let set = clipboard.set();
+        #[cfg(target_os = "linux")]
+        let set = {
+            use arboard::SetExtLinux;
+            set.clipboard(Self::linux_kind(kind))
+        };
+        #[cfg(not(target_os = "linux"))]
+        let _ = kind;
+
+        if let Err(e) = set.text(text) {
+            log::warn!("Falha ao escrever no clipboard: {}", e);
+        }
+    }
+
+    /// Lê o conteúdo de uma seleção
+    pub fn load(&mut self, kind: ClipboardKind) -> Option<String> {
+        let clipboard = self.inner.as_mut()?;
+
+        let get = clipboard.get();
+        #[cfg(target_os = "linux")]
+        let get = {
+            use arboard::GetExtLinux;
+            get.clipboard(Self::linux_kind(kind))
+        };
+        #[cfg(not(target_os = "linux"))]
+        let _ = kind;
+
+        get.text()
+            .map_err(|e| log::debug!("Falha ao ler o clipboard: {}", e))
+            .ok()
+    }
+
+    #[cfg(target_os = "linux")]
+    fn linux_kind(kind: ClipboardKind) -> arboard::LinuxClipboardKind {
+        match kind {
+            ClipboardKind::Clipboard => arboard::LinuxClipboardKind::Clipboard,
+            ClipboardKind::Primary => arboard::LinuxClipboardKind::Primary,
+        }
+    }
+}
+
+impl Default for Clipboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,5 +1,10 @@
-/// Configurações do RTerm
-/// Visual refinado com tema Catppuccin Mocha e tipografia otimizada
+//! Configurações do RTerm
+//! Visual refinado com tema Catppuccin Mocha e tipografia otimizada
+
+/// Título padrão da janela
+pub const DEFAULT_TITLE: &str = "RTerm";
+/// Acrescenta o diretório atual do shell (OSC 7) ao título
+pub const TITLE_WITH_CWD: bool = true;
 
 /// Dimensões padrão da janela
 pub const DEFAULT_WIDTH: u32 = 1280;
@@ -54,8 +59,94 @@ pub const ANSI_COLORS: [[f32; 4]; 16] = [
     [0.655, 0.686, 0.776, 1.0],     // 15: Subtext0 #a6adc8
 ];
 
+/// Paleta xterm de 256 cores
+/// 0-15: ANSI_COLORS, 16-231: cubo 6x6x6, 232-255: rampa de cinza
+pub const PALETTE_256: [[f32; 4]; 256] = build_palette();
+
+const fn build_palette() -> [[f32; 4]; 256] {
+    // Níveis do cubo xterm: 0, 95, 135, 175, 215, 255
+    const fn cube_level(i: usize) -> f32 {
+        if i == 0 { 0.0 } else { (55 + i * 40) as f32 / 255.0 }
+    }
+
+    let mut palette = [[0.0, 0.0, 0.0, 1.0]; 256];
+
+    let mut i = 0;
+    while i < 16 {
+        palette[i] = ANSI_COLORS[i];
+        i += 1;
+    }
+
+    while i < 232 {
+        let n = i - 16;
+        palette[i] = [cube_level(n / 36), cube_level((n / 6) % 6), cube_level(n % 6), 1.0];
+        i += 1;
+    }
+
+    while i < 256 {
+        let level = (8 + (i - 232) * 10) as f32 / 255.0;
+        palette[i] = [level, level, level, 1.0];
+        i += 1;
+    }
+
+    palette
+}
+
 /// Performance settings
 pub const SCROLLBACK_LINES: usize = 10_000;
 
+/// Intervalo das tab stops padrão
+pub const TAB_WIDTH: usize = 8;
+
 /// Visual settings
 pub const CURSOR_BLINK_RATE_MS: u64 = 700;  // Blink mais lento e suave
+/// Período completo (fade out + fade in) do texto piscante (SGR 5)
+pub const TEXT_BLINK_PERIOD_MS: u64 = 1400;
+
+/// Quando mostrar notificações (OSC 9 / OSC 777)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NotifyWhen {
+    /// Só com a janela fora de foco
+    Unfocused,
+    /// Sempre, mesmo com a janela em foco
+    Always,
+}
+
+impl NotifyWhen {
+    /// Política de `RTERM_NOTIFY` (`unfocused` ou `always`), padrão `unfocused`
+    pub fn from_env() -> Self {
+        match std::env::var("RTERM_NOTIFY").as_deref() {
+            Ok("always") => Self::Always,
+            _ => Self::Unfocused,
+        }
+    }
+}
+
+// ============================================================================
+// Clipboard (OSC 52)
+// ============================================================================
+
+/// Tamanho máximo, já decodificado, de uma escrita ou resposta de clipboard
+pub const CLIPBOARD_MAX_BYTES: usize = 512 * 1024;
+
+/// Permissão de leitura do clipboard por programas no terminal
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClipboardRead {
+    /// Ignora consultas
+    Deny,
+    /// Pergunta ao usuário em um overlay
+    Ask,
+    /// Responde sempre
+    Allow,
+}
+
+impl ClipboardRead {
+    /// Política de `RTERM_CLIPBOARD_READ` (`deny`, `ask` ou `allow`), padrão `ask`
+    pub fn from_env() -> Self {
+        match std::env::var("RTERM_CLIPBOARD_READ").as_deref() {
+            Ok("deny") => Self::Deny,
+            Ok("allow") => Self::Allow,
+            _ => Self::Ask,
+        }
+    }
+}
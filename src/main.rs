@@ -1,24 +1,29 @@
-/// RTerm - Terminal de Alta Performance para Apple Silicon
-/// GPU-accelerated via wgpu/Metal
+//! RTerm - Terminal de Alta Performance para Apple Silicon
+//! GPU-accelerated via wgpu/Metal
 
+mod clipboard;
 mod config;
+mod notify;
 mod pty;
 mod term;
 mod renderer;
 
 use anyhow::Result;
 use crossbeam_channel::TryRecvError;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use winit::{
     event::*,
     event_loop::{EventLoop, ControlFlow},
-    keyboard::{Key, NamedKey},
+    keyboard::{Key, ModifiersState, NamedKey},
     window::WindowBuilder,
 };
 
-use config::{DEFAULT_WIDTH, DEFAULT_HEIGHT};
+use clipboard::Clipboard;
+use config::{ClipboardRead, NotifyWhen, DEFAULT_TITLE, DEFAULT_WIDTH, DEFAULT_HEIGHT, TITLE_WITH_CWD};
+use notify::Notifier;
 use pty::{Pty, PtyEvent};
-use term::{Grid, AnsiParser};
+use term::{Grid, AnsiParser, ClipboardKind, TermEvent};
 use renderer::Renderer;
 
 fn main() -> Result<()> {
@@ -29,7 +34,7 @@ fn main() -> Result<()> {
     // Cria a janela
     let window = Arc::new(
         WindowBuilder::new()
-            .with_title("RTerm")
+            .with_title(DEFAULT_TITLE)
             .with_inner_size(winit::dpi::LogicalSize::new(DEFAULT_WIDTH, DEFAULT_HEIGHT))
             .build(&event_loop)?
     );
@@ -41,9 +46,28 @@ fn main() -> Result<()> {
     let (cols, rows) = renderer.grid_dimensions();
     let mut grid = Grid::new(cols, rows);
     
-    // Inicializa PTY
-    let mut pty = Pty::new(cols as u16, rows as u16)?;
+    // Inicializa PTY, no diretório de `--working-directory` se informado
+    let working_directory = working_directory_arg();
+    let pty = Pty::new(cols as u16, rows as u16, working_directory.as_deref())?;
     let mut parser = AnsiParser::new();
+
+    // Título definido pelo programa, combinado com o diretório atual
+    let mut title = DEFAULT_TITLE.to_string();
+    window.set_title(&window_title(&title, pty.cwd().as_deref()));
+
+    // Clipboard (OSC 52) e leitura aguardando confirmação no overlay
+    let mut clipboard = Clipboard::new();
+    let clipboard_read = ClipboardRead::from_env();
+    let mut pending_clipboard_read: Option<(ClipboardKind, &'static [u8])> = None;
+
+    // Notificações (OSC 9 / OSC 777), suprimidas com a janela em foco salvo configuração
+    let notifier = Notifier::new();
+    let notify_when = NotifyWhen::from_env();
+    let mut focused = true;
+
+    // Mouse e modificadores para hyperlinks (OSC 8)
+    let mut modifiers = ModifiersState::empty();
+    let mut mouse_cell: Option<(usize, usize)> = None;
     
     // Loop principal
     event_loop.run(move |event, elwt| {
@@ -54,8 +78,49 @@ fn main() -> Result<()> {
             match pty.rx.try_recv() {
                 Ok(PtyEvent::Output(data)) => {
                     parser.process(&data, &mut grid);
+
+                    // Respostas a consultas (DA, DSR, CPR)
+                    let reply = parser.take_output();
+                    if !reply.is_empty() {
+                        pty.write(&reply);
+                    }
+
+                    for event in parser.take_events() {
+                        match event {
+                            TermEvent::Title(new) => {
+                                title = new;
+                                window.set_title(&window_title(&title, current_dir(&parser, &pty).as_deref()));
+                            }
+                            TermEvent::WorkingDirectory(dir) => {
+                                window.set_title(&window_title(&title, Some(&dir)));
+                            }
+                            TermEvent::Notification { title: summary, body } => {
+                                if !focused || notify_when == NotifyWhen::Always {
+                                    notifier.notify(summary, body);
+                                }
+                            }
+                            TermEvent::ClipboardStore(kind, text) => clipboard.store(kind, &text),
+                            TermEvent::ClipboardLoad(kind, terminator) => match clipboard_read {
+                                ClipboardRead::Deny => log::debug!("Leitura do clipboard negada"),
+                                ClipboardRead::Allow => {
+                                    reply_clipboard(&mut clipboard, &pty, kind, terminator);
+                                }
+                                // Uma confirmação por vez: leituras concorrentes são recusadas
+                                ClipboardRead::Ask if pending_clipboard_read.is_some() => {
+                                    log::debug!("Leitura do clipboard recusada: confirmação pendente");
+                                }
+                                ClipboardRead::Ask => {
+                                    pending_clipboard_read = Some((kind, terminator));
+                                    renderer.set_overlay(Some(
+                                        "Permitir que o programa leia o clipboard? (y/n)".to_string(),
+                                    ));
+                                }
+                            },
+                        }
+                    }
                 }
-                Ok(PtyEvent::Exit(_)) => {
+                Ok(PtyEvent::Exit(code)) => {
+                    log::info!("Shell encerrado (código {})", code);
                     elwt.exit();
                     return;
                 }
@@ -74,6 +139,37 @@ fn main() -> Result<()> {
                         elwt.exit();
                     }
                     
+                    WindowEvent::Focused(new) => {
+                        focused = new;
+                        renderer.set_focused(focused);
+                    }
+
+                    WindowEvent::ModifiersChanged(new) => {
+                        modifiers = new.state();
+                    }
+
+                    WindowEvent::CursorMoved { position, .. } => {
+                        mouse_cell = renderer.cell_at(position.x, position.y);
+                    }
+
+                    WindowEvent::CursorLeft { .. } => {
+                        mouse_cell = None;
+                    }
+
+                    WindowEvent::MouseInput {
+                        state: ElementState::Pressed,
+                        button: MouseButton::Left,
+                        ..
+                    } if modifiers.control_key() => {
+                        // Ctrl+click abre o hyperlink
+                        let link = mouse_cell
+                            .and_then(|(x, y)| grid.hyperlink_at(x, y))
+                            .and_then(|id| grid.hyperlinks.get(id));
+                        if let Some(link) = link {
+                            open_uri(&link.uri);
+                        }
+                    }
+
                     WindowEvent::Resized(physical_size) => {
                         renderer.resize(physical_size);
                         let (cols, rows) = renderer.grid_dimensions();
@@ -90,16 +186,81 @@ fn main() -> Result<()> {
                         },
                         ..
                     } => {
+                        // Confirmação pendente consome a tecla: só 'y' permite
+                        // Modificadores sozinhos não respondem ao prompt
+                        if pending_clipboard_read.is_some() && is_modifier(&logical_key) {
+                            return;
+                        }
+                        if let Some((kind, terminator)) = pending_clipboard_read.take() {
+                            renderer.set_overlay(None);
+                            if matches!(&logical_key, Key::Character(c) if c.as_str() == "y") {
+                                reply_clipboard(&mut clipboard, &pty, kind, terminator);
+                            }
+                            return;
+                        }
+
+                        // Atalhos Ctrl+Shift não chegam ao PTY
+                        if modifiers.control_key() && modifiers.shift_key() {
+                            let key = match &logical_key {
+                                Key::Character(c) => c.to_ascii_lowercase(),
+                                _ => String::new(),
+                            };
+                            let handled = match (&logical_key, key.as_str()) {
+                                // Prompt anterior / seguinte (OSC 133)
+                                (Key::Named(NamedKey::ArrowUp), _) => {
+                                    grid.jump_to_prompt(false);
+                                    true
+                                }
+                                (Key::Named(NamedKey::ArrowDown), _) => {
+                                    grid.jump_to_prompt(true);
+                                    true
+                                }
+                                // Seleciona a saída de um comando
+                                (_, "o") => {
+                                    grid.select_command_output();
+                                    true
+                                }
+                                // Copia a seleção
+                                (_, "c") => {
+                                    if let Some(text) = grid.selection_text() {
+                                        clipboard.store(ClipboardKind::Clipboard, &text);
+                                    }
+                                    true
+                                }
+                                // Copia a saída do último comando
+                                (_, "l") => {
+                                    if let Some(text) = grid.last_output_text() {
+                                        clipboard.store(ClipboardKind::Clipboard, &text);
+                                    }
+                                    true
+                                }
+                                // Outra janela no diretório atual
+                                (_, "n") => {
+                                    spawn_window(current_dir(&parser, &pty).as_deref());
+                                    true
+                                }
+                                _ => false,
+                            };
+                            if handled {
+                                return;
+                            }
+                        }
+
+                        // Setas em modo DECCKM usam SS3 em vez de CSI
+                        let arrow_prefix = if grid.modes.cursor_keys { b'O' } else { b'[' };
+                        let arrow = |c: u8| vec![0x1b, arrow_prefix, c];
+
                         // Converte key para bytes
                         let bytes: Option<Vec<u8>> = match &logical_key {
+                            Key::Named(NamedKey::Enter) if grid.modes.linefeed_newline => Some(b"\r\n".to_vec()),
                             Key::Named(NamedKey::Enter) => Some(vec![b'\r']),
                             Key::Named(NamedKey::Backspace) => Some(vec![0x7f]),
                             Key::Named(NamedKey::Tab) => Some(vec![b'\t']),
                             Key::Named(NamedKey::Escape) => Some(vec![0x1b]),
-                            Key::Named(NamedKey::ArrowUp) => Some(b"\x1b[A".to_vec()),
-                            Key::Named(NamedKey::ArrowDown) => Some(b"\x1b[B".to_vec()),
-                            Key::Named(NamedKey::ArrowRight) => Some(b"\x1b[C".to_vec()),
-                            Key::Named(NamedKey::ArrowLeft) => Some(b"\x1b[D".to_vec()),
+                            Key::Named(NamedKey::ArrowUp) => Some(arrow(b'A')),
+                            Key::Named(NamedKey::ArrowDown) => Some(arrow(b'B')),
+                            Key::Named(NamedKey::ArrowRight) => Some(arrow(b'C')),
+                            Key::Named(NamedKey::ArrowLeft) => Some(arrow(b'D')),
                             Key::Named(NamedKey::Home) => Some(b"\x1b[H".to_vec()),
                             Key::Named(NamedKey::End) => Some(b"\x1b[F".to_vec()),
                             Key::Named(NamedKey::PageUp) => Some(b"\x1b[5~".to_vec()),
@@ -112,14 +273,24 @@ fn main() -> Result<()> {
                         };
 
                         if let Some(data) = bytes {
-                            let _ = pty.write(&data);
+                            // Digitar volta à tela ao vivo e desfaz a seleção
+                            grid.reset_display();
+                            grid.clear_selection();
+                            pty.write(&data);
                         }
                     }
 
                     WindowEvent::RedrawRequested => {
+                        // Hover recalculado a cada frame: o texto pode ter rolado
+                        let hover = mouse_cell.and_then(|(x, y)| {
+                            grid.hyperlink_at(x, y).map(|id| (id, x, y))
+                        });
+                        renderer.set_hover(hover);
+
                         if let Err(e) = renderer.render(&grid) {
                             log::error!("Erro de renderização: {:?}", e);
                         }
+                        grid.mark_clean();
                     }
 
                     _ => {}
@@ -135,4 +306,99 @@ fn main() -> Result<()> {
     })?;
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Responde a uma leitura de OSC 52 com o conteúdo atual da seleção
+fn reply_clipboard(clipboard: &mut Clipboard, pty: &Pty, kind: ClipboardKind, terminator: &[u8]) {
+    let text = clipboard.load(kind).unwrap_or_default();
+    pty.write(&AnsiParser::clipboard_reply(kind, &text, terminator));
+}
+
+/// Tecla modificadora pressionada sozinha
+fn is_modifier(key: &Key) -> bool {
+    matches!(
+        key,
+        Key::Named(
+            NamedKey::Shift
+                | NamedKey::Control
+                | NamedKey::Alt
+                | NamedKey::AltGraph
+                | NamedKey::Super
+                | NamedKey::Meta
+                | NamedKey::Hyper
+                | NamedKey::CapsLock
+                | NamedKey::NumLock
+                | NamedKey::Fn
+        )
+    )
+}
+
+/// Abre uma URI com o handler padrão do sistema
+fn open_uri(uri: &str) {
+    // Evita que a URI seja interpretada como opção
+    if uri.starts_with('-') {
+        log::warn!("URI recusada: {}", uri);
+        return;
+    }
+
+    match std::process::Command::new("xdg-open").arg(uri).spawn() {
+        Ok(mut child) => {
+            // Colhe o processo sem bloquear o loop de eventos
+            std::thread::spawn(move || child.wait());
+        }
+        Err(e) => log::warn!("Falha ao abrir {}: {}", uri, e),
+    }
+}
+
+/// Diretório de `--working-directory <dir>` na linha de comando
+fn working_directory_arg() -> Option<PathBuf> {
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--working-directory" {
+            return args.next().map(PathBuf::from);
+        }
+    }
+    None
+}
+
+/// Diretório atual do shell: OSC 7, ou /proc/<pid>/cwd como fallback
+fn current_dir(parser: &AnsiParser, pty: &Pty) -> Option<PathBuf> {
+    parser.cwd().map(Path::to_path_buf).or_else(|| pty.cwd())
+}
+
+/// Título da janela, com o diretório abreviado por `~` quando habilitado
+fn window_title(title: &str, cwd: Option<&Path>) -> String {
+    let Some(cwd) = cwd.filter(|_| TITLE_WITH_CWD) else {
+        return title.to_string();
+    };
+
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let dir = match home.as_deref().and_then(|home| cwd.strip_prefix(home).ok()) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~/{}", rest.display()),
+        None => cwd.display().to_string(),
+    };
+    format!("{} — {}", title, dir)
+}
+
+/// Abre uma nova janela do RTerm iniciando em `cwd`
+fn spawn_window(cwd: Option<&Path>) {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            log::warn!("Executável do RTerm não encontrado: {}", e);
+            return;
+        }
+    };
+
+    let mut cmd = std::process::Command::new(exe);
+    if let Some(dir) = cwd {
+        cmd.arg("--working-directory").arg(dir);
+    }
+    match cmd.spawn() {
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(e) => log::warn!("Falha ao abrir nova janela: {}", e),
+    }
+}
@@ -0,0 +1,168 @@
+//! Notificações de desktop
+//! Encaminha para org.freedesktop.Notifications no D-Bus de sessão, em uma
+//! thread própria para não bloquear o loop de eventos. A conexão é injetável,
+//! o que permite apontar para um daemon de teste
+
+use std::collections::HashMap;
+use std::thread;
+use crossbeam_channel::{bounded, Receiver, Sender};
+use zbus::blocking::Connection;
+use zbus::zvariant::Value;
+use crate::config::DEFAULT_TITLE;
+
+/// Notificação pendente
+struct Notification {
+    summary: String,
+    body: String,
+}
+
+/// Envia notificações pelo D-Bus
+pub struct Notifier {
+    tx: Sender<Notification>,
+}
+
+impl Notifier {
+    /// Notificações no bus de sessão (`DBUS_SESSION_BUS_ADDRESS`)
+    pub fn new() -> Self {
+        Self::connecting_with(Connection::session)
+    }
+
+    /// Notificações na conexão aberta por `connect`, chamada sob demanda
+    fn connecting_with(connect: impl Fn() -> zbus::Result<Connection> + Send + 'static) -> Self {
+        let (tx, rx) = bounded(64);
+        thread::spawn(move || Self::send_loop(rx, connect));
+        Self { tx }
+    }
+
+    /// Enfileira uma notificação; descarta se a fila estiver cheia
+    pub fn notify(&self, summary: String, body: String) {
+        if self.tx.try_send(Notification { summary, body }).is_err() {
+            log::debug!("Fila de notificações cheia, descartada");
+        }
+    }
+
+    /// Conecta sob demanda e reconecta após falhas
+    fn send_loop(rx: Receiver<Notification>, connect: impl Fn() -> zbus::Result<Connection>) {
+        let mut connection: Option<Connection> = None;
+        for notification in rx {
+            if connection.is_none() {
+                connection = connect()
+                    .map_err(|e| log::warn!("D-Bus de sessão indisponível: {}", e))
+                    .ok();
+            }
+            let Some(conn) = &connection else {
+                continue;
+            };
+            if let Err(e) = Self::send(conn, &notification) {
+                log::warn!("Falha ao enviar notificação: {}", e);
+                connection = None;
+            }
+        }
+    }
+
+    fn send(conn: &Connection, notification: &Notification) -> zbus::Result<()> {
+        let actions: Vec<&str> = Vec::new();
+        let hints: HashMap<&str, Value> = HashMap::new();
+        conn.call_method(
+            Some("org.freedesktop.Notifications"),
+            "/org/freedesktop/Notifications",
+            Some("org.freedesktop.Notifications"),
+            "Notify",
+            &(
+                DEFAULT_TITLE,
+                0u32, // replaces_id
+                "",   // app_icon
+                notification.summary.as_str(),
+                notification.body.as_str(),
+                actions,
+                hints,
+                -1i32, // expire_timeout padrão do servidor
+            ),
+        )?;
+        Ok(())
+    }
+}
+
+impl Default for Notifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+    use std::time::Duration;
+    use zbus::zvariant::OwnedValue;
+
+    /// Responde ao `Hello` que o cliente envia ao "bus"
+    struct MockBus;
+
+    #[zbus::interface(name = "org.freedesktop.DBus")]
+    impl MockBus {
+        fn hello(&self) -> String {
+            ":1.1".to_string()
+        }
+    }
+
+    /// Daemon de notificações que repassa (app, summary, body) ao teste
+    struct MockNotifications(Sender<(String, String, String)>);
+
+    #[zbus::interface(name = "org.freedesktop.Notifications")]
+    impl MockNotifications {
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &self,
+            app_name: String,
+            _replaces_id: u32,
+            _app_icon: String,
+            summary: String,
+            body: String,
+            _actions: Vec<String>,
+            _hints: HashMap<String, OwnedValue>,
+            _expire_timeout: i32,
+        ) -> u32 {
+            let _ = self.0.send((app_name, summary, body));
+            1
+        }
+    }
+
+    #[test]
+    fn notifies_mock_daemon() {
+        let path = std::env::temp_dir().join(format!("rterm-notify-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+
+        let (tx, rx) = bounded(1);
+        let (done_tx, done_rx) = bounded::<()>(1);
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let _daemon = zbus::blocking::connection::Builder::unix_stream(stream)
+                .server(zbus::Guid::generate())
+                .unwrap()
+                .p2p()
+                .serve_at("/org/freedesktop/DBus", MockBus)
+                .unwrap()
+                .serve_at("/org/freedesktop/Notifications", MockNotifications(tx))
+                .unwrap()
+                .build()
+                .unwrap();
+            let _ = done_rx.recv();
+        });
+
+        let address = format!("unix:path={}", path.display());
+        let notifier = Notifier::connecting_with(move || {
+            zbus::blocking::connection::Builder::address(address.as_str())?.build()
+        });
+        notifier.notify("Build".to_string(), "concluído".to_string());
+
+        let received = rx.recv_timeout(Duration::from_secs(10));
+        let _ = done_tx.send(());
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(
+            received.unwrap(),
+            (DEFAULT_TITLE.to_string(), "Build".to_string(), "concluído".to_string()),
+        );
+    }
+}
@@ -1,12 +1,13 @@
-/// PTY Manager - Backend de shell assíncrono
-/// Usa portable-pty com comunicação via channels
+//! PTY Manager - Backend de shell assíncrono
+//! Usa portable-pty com comunicação via channels
 
 use anyhow::Result;
-use crossbeam_channel::{bounded, Receiver, Sender};
+use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
 use portable_pty::{native_pty_system, CommandBuilder, PtyPair, PtySize};
 use std::io::{Read, Write};
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
 use std::thread;
+use crate::config::{CELL_HEIGHT, CELL_WIDTH};
 
 /// Mensagens do PTY para o terminal
 pub enum PtyEvent {
@@ -17,22 +18,21 @@ pub enum PtyEvent {
 /// Gerenciador do PTY
 pub struct Pty {
     pair: PtyPair,
-    writer: Box<dyn Write + Send>,
+    /// Input para a thread de escrita; o event loop nunca bloqueia no PTY
+    input: Sender<Vec<u8>>,
     pub rx: Receiver<PtyEvent>,
+    /// PID do shell, para ler o diretório atual em /proc
+    child_pid: Option<u32>,
     _reader_thread: thread::JoinHandle<()>,
+    _writer_thread: thread::JoinHandle<()>,
 }
 
 impl Pty {
-    /// Cria um novo PTY com o shell padrão
-    pub fn new(cols: u16, rows: u16) -> Result<Self> {
+    /// Cria um novo PTY com o shell padrão, iniciando em `cwd` (padrão: home)
+    pub fn new(cols: u16, rows: u16, cwd: Option<&Path>) -> Result<Self> {
         let pty_system = native_pty_system();
         
-        let pair = pty_system.openpty(PtySize {
-            rows,
-            cols,
-            pixel_width: 0,
-            pixel_height: 0,
-        })?;
+        let pair = pty_system.openpty(Self::size(cols, rows))?;
 
         // Detecta o shell padrão
         let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/zsh".to_string());
@@ -40,9 +40,13 @@ impl Pty {
         let mut cmd = CommandBuilder::new(&shell);
         cmd.env("TERM", "xterm-256color");
         cmd.env("COLORTERM", "truecolor");
+        if let Some(dir) = cwd {
+            cmd.cwd(dir);
+        }
         
         // Spawn do processo filho
-        let _child = pair.slave.spawn_command(cmd)?;
+        let child = pair.slave.spawn_command(cmd)?;
+        let child_pid = child.process_id();
         
         // Configura comunicação
         let reader = pair.master.try_clone_reader()?;
@@ -55,11 +59,21 @@ impl Pty {
             Self::read_loop(reader, tx);
         });
 
+        // Thread de escrita: um programa que não lê o stdin enche o buffer
+        // do PTY e bloquearia quem escreve. A fila não tem limite para não
+        // perder teclas nem respostas (DA, DSR, CPR) enquanto isso
+        let (input, input_rx) = unbounded();
+        let writer_thread = thread::spawn(move || {
+            Self::write_loop(writer, input_rx);
+        });
+
         Ok(Self {
             pair,
-            writer,
+            input,
             rx,
+            child_pid,
             _reader_thread: reader_thread,
+            _writer_thread: writer_thread,
         })
     }
 
@@ -83,21 +97,42 @@ impl Pty {
         }
     }
 
-    /// Envia input para o PTY
-    pub fn write(&mut self, data: &[u8]) -> Result<()> {
-        self.writer.write_all(data)?;
-        self.writer.flush()?;
-        Ok(())
+    /// Loop de escrita em thread separada, até o PTY fechar
+    fn write_loop(mut writer: Box<dyn Write + Send>, rx: Receiver<Vec<u8>>) {
+        for data in rx {
+            if let Err(e) = writer.write_all(&data).and_then(|_| writer.flush()) {
+                log::error!("Erro ao escrever no PTY: {}", e);
+                break;
+            }
+        }
+    }
+
+    /// Envia input para o PTY sem bloquear
+    pub fn write(&self, data: &[u8]) {
+        if self.input.send(data.to_vec()).is_err() {
+            log::warn!("PTY fechado; {} bytes descartados", data.len());
+        }
+    }
+
+    /// Diretório atual do shell via /proc/<pid>/cwd
+    pub fn cwd(&self) -> Option<PathBuf> {
+        let pid = self.child_pid?;
+        std::fs::read_link(format!("/proc/{}/cwd", pid)).ok()
     }
 
     /// Redimensiona o PTY
     pub fn resize(&self, cols: u16, rows: u16) -> Result<()> {
-        self.pair.master.resize(PtySize {
+        self.pair.master.resize(Self::size(cols, rows))?;
+        Ok(())
+    }
+
+    /// Tamanho em células e em pixels da área de texto (sixel usa os pixels)
+    fn size(cols: u16, rows: u16) -> PtySize {
+        PtySize {
             rows,
             cols,
-            pixel_width: 0,
-            pixel_height: 0,
-        })?;
-        Ok(())
+            pixel_width: (cols as f32 * CELL_WIDTH) as u16,
+            pixel_height: (rows as f32 * CELL_HEIGHT) as u16,
+        }
     }
 }
@@ -0,0 +1,109 @@
+//! Texture atlas RGBA com alocação em prateleiras
+//! Base do cache de glyphs e do cache de imagens
+
+/// Atlas quadrado: regiões alocadas da esquerda para a direita, em
+/// prateleiras da altura do item mais alto
+pub struct Atlas {
+    texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    size: u32,
+    next_x: u32,
+    next_y: u32,
+    row_height: u32,
+}
+
+impl Atlas {
+    pub fn new(device: &wgpu::Device, label: &str, size: u32) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        Self {
+            texture,
+            view,
+            size,
+            next_x: 0,
+            next_y: 0,
+            row_height: 0,
+        }
+    }
+
+    /// Lado do atlas em pixels
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Reserva uma região `width` x `height`; `None` se o atlas estiver cheio
+    pub fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        // Próxima prateleira se não couber
+        if self.next_x + width > self.size {
+            self.next_x = 0;
+            self.next_y += self.row_height + 1;
+            self.row_height = 0;
+        }
+
+        if width > self.size || self.next_y + height > self.size {
+            return None;
+        }
+
+        let position = (self.next_x, self.next_y);
+        // 1 pixel de margem evita vazamento na filtragem linear
+        self.next_x += width + 1;
+        self.row_height = self.row_height.max(height);
+        Some(position)
+    }
+
+    /// Libera todas as regiões; o conteúdo antigo é sobrescrito sob demanda
+    pub fn clear(&mut self) {
+        self.next_x = 0;
+        self.next_y = 0;
+        self.row_height = 0;
+    }
+
+    /// Envia pixels RGBA para uma região alocada
+    pub fn write(&self, queue: &wgpu::Queue, x: u32, y: u32, width: u32, height: u32, rgba: &[u8]) {
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            rgba,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(width * 4),
+                rows_per_image: Some(height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// UV coords (u0, v0, u1, v1) de uma região
+    pub fn uv(&self, x: u32, y: u32, width: u32, height: u32) -> (f32, f32, f32, f32) {
+        let size = self.size as f32;
+        (
+            x as f32 / size,
+            y as f32 / size,
+            (x + width) as f32 / size,
+            (y + height) as f32 / size,
+        )
+    }
+}
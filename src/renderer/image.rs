@@ -0,0 +1,102 @@
+//! Image Cache - Imagens do grid na GPU
+//! Ficam num atlas próprio, criado com a primeira imagem e dobrado de tamanho
+//! quando as imagens visíveis não cabem
+
+use std::collections::HashMap;
+use crate::term::Image;
+use crate::term::image::IMAGE_MAX_SIZE;
+use super::atlas::Atlas;
+
+/// Lado do atlas criado para a primeira imagem
+const ATLAS_MIN_SIZE: u32 = 512;
+
+/// Cache de imagens com texture atlas
+pub struct ImageCache {
+    /// Atlas de 1x1 até a primeira imagem; cresce até `IMAGE_MAX_SIZE`
+    pub atlas: Atlas,
+    /// Posição no atlas de cada imagem enviada, por id
+    positions: HashMap<u64, (u32, u32)>,
+    /// Ids (ordenados) do último conjunto visível que não coube nem no
+    /// atlas máximo; não é reempacotado até o conjunto mudar
+    failed: Option<Vec<u64>>,
+}
+
+impl ImageCache {
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            atlas: Atlas::new(device, "Image Atlas", 1),
+            positions: HashMap::new(),
+            failed: None,
+        }
+    }
+
+    /// Garante que as imagens visíveis estejam no atlas
+    /// Se não couberem, o atlas recomeça só com elas, dobrando de tamanho
+    /// enquanto for preciso; `true` se a textura foi trocada
+    pub fn prepare(&mut self, images: &[&Image], device: &wgpu::Device, queue: &wgpu::Queue) -> bool {
+        // Maiores que o atlas máximo nunca cabem (e nunca são desenhadas)
+        let images: Vec<&Image> = images
+            .iter()
+            .copied()
+            .filter(|image| image.width <= IMAGE_MAX_SIZE && image.height <= IMAGE_MAX_SIZE)
+            .collect();
+
+        // Ids são únicos por imagem: o mesmo conjunto falharia de novo
+        let mut ids: Vec<u64> = images.iter().map(|image| image.id).collect();
+        ids.sort_unstable();
+        if self.failed.as_ref() == Some(&ids) {
+            return false;
+        }
+        self.failed = None;
+
+        let missing = images
+            .iter()
+            .any(|image| !self.positions.contains_key(&image.id) && !self.upload(image, queue));
+        if !missing {
+            return false;
+        }
+
+        // Reempacota no tamanho atual antes de crescer: imagens que saíram da
+        // tela liberam espaço
+        let mut replaced = false;
+        loop {
+            self.atlas.clear();
+            self.positions.clear();
+            let fits = images.iter().all(|image| self.upload(image, queue));
+            let size = self.atlas.size();
+            if fits || size >= IMAGE_MAX_SIZE {
+                if !fits {
+                    log::warn!("Imagens visíveis não cabem no atlas de {}x{}", size, size);
+                    self.failed = Some(ids);
+                }
+                return replaced;
+            }
+
+            let largest = images.iter().map(|image| image.width.max(image.height)).max().unwrap_or(0);
+            let size = (size * 2).max(largest.next_power_of_two()).clamp(ATLAS_MIN_SIZE, IMAGE_MAX_SIZE);
+            log::debug!("Atlas de imagens com {}x{}", size, size);
+            self.atlas = Atlas::new(device, "Image Atlas", size);
+            replaced = true;
+        }
+    }
+
+    /// Envia uma imagem para o atlas; `false` se não houver espaço
+    fn upload(&mut self, image: &Image, queue: &wgpu::Queue) -> bool {
+        let Some((x, y)) = self.atlas.allocate(image.width, image.height) else {
+            return false;
+        };
+        self.atlas.write(queue, x, y, image.width, image.height, &image.data);
+        self.positions.insert(image.id, (x, y));
+        true
+    }
+
+    /// UV coords de um recorte (x, y, largura, altura) de uma imagem já enviada
+    pub fn uv(&self, image: &Image, source: [f32; 4]) -> Option<(f32, f32, f32, f32)> {
+        let &(x, y) = self.positions.get(&image.id)?;
+        let size = self.atlas.size() as f32;
+        let [source_x, source_y, width, height] = source;
+        let u0 = (x as f32 + source_x) / size;
+        let v0 = (y as f32 + source_y) / size;
+        Some((u0, v0, u0 + width / size, v0 + height / size))
+    }
+}
@@ -1,13 +1,36 @@
-/// Módulo de renderização GPU
-/// wgpu com backend Metal para Apple Silicon
+//! Módulo de renderização GPU
+//! wgpu com backend Metal para Apple Silicon
 
+pub mod atlas;
 pub mod glyph;
+pub mod image;
 
 use anyhow::Result;
 use wgpu::util::DeviceExt;
-use crate::config::{BG_COLOR, CELL_WIDTH, CELL_HEIGHT, PADDING_X, PADDING_Y, CURSOR_COLOR, CURSOR_TEXT_COLOR};
-use crate::term::Grid;
+use crate::config::{CELL_WIDTH, CELL_HEIGHT, PADDING_X, PADDING_Y, CURSOR_TEXT_COLOR};
+use crate::term::{Grid, Attrs, CellWidth, CursorShape, Image, LinkId, Underline};
+use crate::term::grid::Cell;
+use crate::term::image::{ImageFragment, Z_BELOW_BACKGROUND};
 use glyph::GlyphCache;
+use image::ImageCache;
+
+/// UV nulo: o shader usa só a cor de background
+const NO_UV: (f32, f32, f32, f32) = (0.0, 0.0, 0.0, 0.0);
+
+/// Sublinhado: espessura e distância abaixo da baseline
+const UNDERLINE_THICKNESS: f32 = 1.0;
+const UNDERLINE_OFFSET: f32 = 2.0;
+
+/// Espessura dos cursores underline e bar
+const CURSOR_THICKNESS: f32 = 2.0;
+
+/// Flags por vertex (espelham FLAG_* em shader.wgsl)
+const FLAG_DIM: u32 = 1;
+const FLAG_BLINK: u32 = 2;
+/// Quad sólido na cor de foreground (decorações)
+const FLAG_SOLID: u32 = 4;
+/// Quad amostrado do atlas de imagens
+const FLAG_IMAGE: u32 = 8;
 
 /// Vertex para renderização de células
 #[repr(C)]
@@ -17,14 +40,24 @@ pub struct Vertex {
     pub tex_coords: [f32; 2],
     pub fg_color: [f32; 4],
     pub bg_color: [f32; 4],
+    pub flags: u32,
+}
+
+/// Uniforms globais do shader
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Globals {
+    blink_alpha: f32,
+    _padding: [f32; 3],
 }
 
 impl Vertex {
-    const ATTRIBS: [wgpu::VertexAttribute; 4] = wgpu::vertex_attr_array![
+    const ATTRIBS: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
         0 => Float32x2,
         1 => Float32x2,
         2 => Float32x4,
         3 => Float32x4,
+        4 => Uint32,
     ];
 
     fn desc() -> wgpu::VertexBufferLayout<'static> {
@@ -46,13 +79,23 @@ pub struct Renderer {
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
     glyph_cache: GlyphCache,
+    image_cache: ImageCache,
+    bind_group_layout: wgpu::BindGroupLayout,
     bind_group: wgpu::BindGroup,
+    globals_buffer: wgpu::Buffer,
     pub size: winit::dpi::PhysicalSize<u32>,
     vertices: Vec<Vertex>,
     indices: Vec<u32>,
     // Estado do cursor
     cursor_visible: bool,
     last_blink: std::time::Instant,
+    /// Janela com foco; sem foco o texto piscante fica estático
+    focused: bool,
+    blink_start: std::time::Instant,
+    /// Mensagem exibida sobre a última linha (ex.: confirmação de clipboard)
+    overlay: Option<String>,
+    /// Link sob o mouse e a célula apontada (coluna, linha)
+    hover: Option<(LinkId, usize, usize)>,
 }
 
 impl Renderer {
@@ -112,6 +155,7 @@ impl Renderer {
 
         // Glyph cache
         let glyph_cache = GlyphCache::new(&device, &queue);
+        let image_cache = ImageCache::new(&device);
 
         // Shader
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
@@ -139,24 +183,47 @@ impl Renderer {
                     ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
                     count: None,
                 },
-            ],
-        });
-
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Glyph Bind Group"),
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&glyph_cache.texture_view),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
                 },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&glyph_cache.sampler),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
                 },
             ],
         });
 
+        // Uniforms globais (opacidade do blink)
+        let globals_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Globals Buffer"),
+            contents: bytemuck::bytes_of(&Globals {
+                blink_alpha: 1.0,
+                _padding: [0.0; 3],
+            }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group = Self::create_bind_group(
+            &device,
+            &bind_group_layout,
+            &glyph_cache,
+            &globals_buffer,
+            &image_cache,
+        );
+
         // Pipeline layout
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Render Pipeline Layout"),
@@ -218,12 +285,51 @@ impl Renderer {
             vertex_buffer,
             index_buffer,
             glyph_cache,
+            image_cache,
+            bind_group_layout,
             bind_group,
+            globals_buffer,
             size,
             vertices: Vec::new(),
             indices: Vec::new(),
             cursor_visible: true,
             last_blink: std::time::Instant::now(),
+            focused: true,
+            blink_start: std::time::Instant::now(),
+            overlay: None,
+            hover: None,
+        })
+    }
+
+    /// Bind group com os atlas atuais; recriado quando o atlas de imagens cresce
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        glyph_cache: &GlyphCache,
+        globals_buffer: &wgpu::Buffer,
+        image_cache: &ImageCache,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Glyph Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&glyph_cache.atlas.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&glyph_cache.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: globals_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&image_cache.atlas.view),
+                },
+            ],
         })
     }
 
@@ -245,6 +351,18 @@ impl Renderer {
         (cols.max(1), rows.max(1))
     }
 
+    /// Célula (coluna, linha) sob uma posição em pixels da janela
+    pub fn cell_at(&self, x: f64, y: f64) -> Option<(usize, usize)> {
+        let col = (x as f32 - PADDING_X) / CELL_WIDTH;
+        let row = (y as f32 - PADDING_Y) / CELL_HEIGHT;
+        if col < 0.0 || row < 0.0 {
+            return None;
+        }
+        let (cols, rows) = self.grid_dimensions();
+        let (col, row) = (col as usize, row as usize);
+        (col < cols && row < rows).then_some((col, row))
+    }
+
     /// Atualiza estado de blink do cursor
     fn update_cursor_blink(&mut self) {
         let elapsed = self.last_blink.elapsed().as_millis() as u64;
@@ -254,9 +372,41 @@ impl Renderer {
         }
     }
 
+    /// Atualiza o estado de foco da janela
+    pub fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+        self.blink_start = std::time::Instant::now();
+    }
+
+    /// Define ou remove a mensagem de overlay
+    pub fn set_overlay(&mut self, text: Option<String>) {
+        self.overlay = text;
+    }
+
+    /// Define o link sob o mouse: sublinhado e tooltip com a URI
+    pub fn set_hover(&mut self, hover: Option<(LinkId, usize, usize)>) {
+        self.hover = hover;
+    }
+
+    /// Opacidade do texto piscante: fade contínuo com foco, estático sem
+    fn text_blink_alpha(&self) -> f32 {
+        if !self.focused {
+            return 1.0;
+        }
+        let period = crate::config::TEXT_BLINK_PERIOD_MS as f32;
+        let t = (self.blink_start.elapsed().as_millis() as f32 % period) / period;
+        0.5 + 0.5 * (t * std::f32::consts::TAU).cos()
+    }
+
     /// Renderiza o grid
     pub fn render(&mut self, grid: &Grid) -> Result<()> {
         self.update_cursor_blink();
+
+        let globals = Globals {
+            blink_alpha: self.text_blink_alpha(),
+            _padding: [0.0; 3],
+        };
+        self.queue.write_buffer(&self.globals_buffer, 0, bytemuck::bytes_of(&globals));
         
         let output = self.surface.get_current_texture()?;
         let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
@@ -279,6 +429,9 @@ impl Renderer {
             });
         }
 
+        // Fundo segue o background dinâmico (OSC 11)
+        let clear_color = grid.palette.background;
+
         let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("Render Encoder"),
         });
@@ -291,10 +444,10 @@ impl Renderer {
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: BG_COLOR[0] as f64,
-                            g: BG_COLOR[1] as f64,
-                            b: BG_COLOR[2] as f64,
-                            a: BG_COLOR[3] as f64,
+                            r: clear_color[0] as f64,
+                            g: clear_color[1] as f64,
+                            b: clear_color[2] as f64,
+                            a: clear_color[3] as f64,
                         }),
                         store: wgpu::StoreOp::Store,
                     },
@@ -323,118 +476,399 @@ impl Renderer {
         self.vertices.clear();
         self.indices.clear();
 
-        let scale_x = 2.0 / self.size.width as f32;
-        let scale_y = 2.0 / self.size.height as f32;
+        // Imagens visíveis precisam estar no atlas antes de gerar os UVs
+        let mut images: Vec<&Image> = Vec::new();
+        for y in 0..grid.rows {
+            for fragment in &grid.visible_row(y).images {
+                if !images.iter().any(|image| image.id == fragment.image.id) {
+                    images.push(&fragment.image);
+                }
+            }
+        }
+        if self.image_cache.prepare(&images, &self.device, &self.queue) {
+            self.bind_group = Self::create_bind_group(
+                &self.device,
+                &self.bind_group_layout,
+                &self.glyph_cache,
+                &self.globals_buffer,
+                &self.image_cache,
+            );
+        }
 
         for y in 0..grid.rows {
+            // Histórico rolado e seleção por linha
+            let row = grid.visible_row(y);
+            let selected = grid.is_selected(y);
+
+            // Imagens em ordem de z (empates mantêm a ordem de chegada), em
+            // três camadas: sob o background, sob o texto e sobre o texto
+            let mut fragments: Vec<&ImageFragment> = row.images.iter().collect();
+            fragments.sort_by_key(|fragment| fragment.z);
+            let below_background = fragments.partition_point(|f| f.z < Z_BELOW_BACKGROUND);
+            let below_text = fragments.partition_point(|f| f.z < 0);
+
+            for fragment in &fragments[..below_background] {
+                self.push_image(fragment, y);
+            }
+
+            for x in 0..grid.cols {
+                let cell = &row[x];
+                if cell.width == CellWidth::Spacer {
+                    continue;
+                }
+
+                // Background só quando difere do clear color
+                let (_, bg) = Self::cell_colors(grid, cell, selected);
+                if bg != grid.palette.background {
+                    let span = if cell.width == CellWidth::Wide { 2.0 } else { 1.0 };
+                    let px = PADDING_X + x as f32 * CELL_WIDTH;
+                    let py = PADDING_Y + y as f32 * CELL_HEIGHT;
+                    self.push_quad(px, py, CELL_WIDTH * span, CELL_HEIGHT, NO_UV, bg, bg, 0);
+                }
+            }
+
+            for fragment in &fragments[below_background..below_text] {
+                self.push_image(fragment, y);
+            }
+
             for x in 0..grid.cols {
-                let cell = grid.get_cell(x, y);
-                
-                // Skip espaços vazios com background padrão
-                if cell.c == ' ' && cell.style.bg == BG_COLOR {
+                let cell = &row[x];
+
+                // Spacers são desenhados junto com o caractere largo
+                if cell.width == CellWidth::Spacer {
                     continue;
                 }
 
-                // Coordenadas em clip space (-1 a 1) com padding
+                let attrs = cell.style.attrs;
+                let (fg, _) = Self::cell_colors(grid, cell, selected);
+
+                let span = if cell.width == CellWidth::Wide { 2.0 } else { 1.0 };
                 let px = PADDING_X + x as f32 * CELL_WIDTH;
                 let py = PADDING_Y + y as f32 * CELL_HEIGHT;
-                
-                let x0 = px * scale_x - 1.0;
-                let y0 = 1.0 - py * scale_y;
-                let x1 = (px + CELL_WIDTH) * scale_x - 1.0;
-                let y1 = 1.0 - (py + CELL_HEIGHT) * scale_y;
-
-                // Obtém UV do glyph
-                let (fg, bg) = if cell.style.inverse {
-                    (cell.style.bg, cell.style.fg)
-                } else {
-                    (cell.style.fg, cell.style.bg)
+                let width = CELL_WIDTH * span;
+
+                // Texto oculto (SGR 8) mantém só o background
+                if attrs.contains(Attrs::HIDDEN) {
+                    continue;
+                }
+
+                let mut flags = 0;
+                if attrs.contains(Attrs::DIM) {
+                    flags |= FLAG_DIM;
+                }
+                if attrs.contains(Attrs::BLINK) {
+                    flags |= FLAG_BLINK;
+                }
+
+                if cell.c != ' ' {
+                    self.push_glyph(cell.c, px, py, width, fg, flags);
+                }
+
+                // Grapheme cluster: marcas combinantes sobre a mesma célula
+                if let Some(zerowidth) = &cell.zerowidth {
+                    for &c in zerowidth.iter() {
+                        self.push_glyph(c, px, py, width, fg, flags);
+                    }
+                }
+
+                // Link sob o mouse ganha sublinhado simples
+                let hovered = cell.style.hyperlink.is_some()
+                    && cell.style.hyperlink == self.hover.map(|(id, ..)| id);
+                let underline = match cell.style.underline {
+                    Underline::None if hovered => Underline::Single,
+                    underline => underline,
                 };
 
-                let uv = self.glyph_cache.get_uv(cell.c);
-                
-                let base = self.vertices.len() as u32;
-                
-                // 4 vertices por célula
-                self.vertices.push(Vertex {
-                    position: [x0, y0],
-                    tex_coords: [uv.0, uv.1],
-                    fg_color: fg,
-                    bg_color: bg,
-                });
-                self.vertices.push(Vertex {
-                    position: [x1, y0],
-                    tex_coords: [uv.2, uv.1],
-                    fg_color: fg,
-                    bg_color: bg,
-                });
-                self.vertices.push(Vertex {
-                    position: [x1, y1],
-                    tex_coords: [uv.2, uv.3],
-                    fg_color: fg,
-                    bg_color: bg,
-                });
-                self.vertices.push(Vertex {
-                    position: [x0, y1],
-                    tex_coords: [uv.0, uv.3],
-                    fg_color: fg,
-                    bg_color: bg,
-                });
-
-                // 2 triângulos por célula
-                self.indices.extend_from_slice(&[
-                    base, base + 1, base + 2,
-                    base, base + 2, base + 3,
-                ]);
+                if underline != Underline::None {
+                    let color = cell
+                        .style
+                        .underline_color
+                        .map_or(fg, |c| grid.palette.resolve(c));
+                    self.push_underline(underline, px, py, width, color, flags);
+                }
+
+                if attrs.contains(Attrs::STRIKETHROUGH) {
+                    let y = py + self.glyph_cache.strikeout();
+                    self.push_line(px, y, width, fg, flags);
+                }
+
+                if attrs.contains(Attrs::OVERLINE) {
+                    let y = py + self.glyph_cache.overline();
+                    self.push_line(px, y, width, fg, flags);
+                }
+            }
+
+            for fragment in &fragments[below_text..] {
+                self.push_image(fragment, y);
+            }
+        }
+
+        // Cursor, oculto via DECTCEM e com o histórico rolado
+        if grid.modes.show_cursor && grid.display_offset() == 0 {
+            self.push_cursor(grid);
+        }
+
+        self.push_tooltip(grid);
+        self.push_overlay(grid);
+    }
+
+    /// Barra de mensagem sobre a última linha, em cores invertidas
+    fn push_overlay(&mut self, grid: &Grid) {
+        let Some(text) = self.overlay.clone() else {
+            return;
+        };
+
+        let py = PADDING_Y + grid.rows.saturating_sub(1) as f32 * CELL_HEIGHT;
+        let width = self.size.width as f32;
+        let bg = grid.palette.foreground;
+        self.push_quad(0.0, py, width, CELL_HEIGHT, NO_UV, bg, bg, 0);
+        self.push_text(&text, 0, py, grid.cols, grid.palette.background);
+    }
+
+    /// URI do link sob o mouse, na linha abaixo (ou acima, na última)
+    fn push_tooltip(&mut self, grid: &Grid) {
+        let Some((id, col, row)) = self.hover else {
+            return;
+        };
+        let Some(link) = grid.hyperlinks.get(id) else {
+            return;
+        };
+        let uri = link.uri.clone();
+
+        let len = uri.chars().count().min(grid.cols);
+        let col = col.min(grid.cols - len);
+        let row = if row + 1 < grid.rows { row + 1 } else { row.saturating_sub(1) };
+        let px = PADDING_X + col as f32 * CELL_WIDTH;
+        let py = PADDING_Y + row as f32 * CELL_HEIGHT;
+
+        let bg = grid.palette.foreground;
+        self.push_quad(px, py, len as f32 * CELL_WIDTH, CELL_HEIGHT, NO_UV, bg, bg, 0);
+        self.push_text(&uri, col, py, len, grid.palette.background);
+    }
+
+    /// Texto de uma linha a partir da coluna `col`, com até `max` caracteres
+    fn push_text(&mut self, text: &str, col: usize, py: f32, max: usize, fg: [f32; 4]) {
+        for (i, c) in text.chars().take(max).enumerate() {
+            let px = PADDING_X + (col + i) as f32 * CELL_WIDTH;
+            self.push_glyph(c, px, py, CELL_WIDTH, fg, 0);
+        }
+    }
+
+    /// Desenha o cursor na forma de DECSCUSR; sem foco vira um block vazado
+    fn push_cursor(&mut self, grid: &Grid) {
+        let style = grid.modes.cursor_style;
+        // Sem foco o cursor fica estático
+        if style.blinking && self.focused && !self.cursor_visible {
+            return;
+        }
+
+        let cx = grid.cursor_x.min(grid.cols - 1);
+        let cy = grid.cursor_y;
+        if cy >= grid.rows {
+            return;
+        }
+
+        let cell = grid.get_cell(cx, cy);
+        let px = PADDING_X + cx as f32 * CELL_WIDTH;
+        let py = PADDING_Y + cy as f32 * CELL_HEIGHT;
+        let span = if cell.width == CellWidth::Wide { 2.0 } else { 1.0 };
+        let width = CELL_WIDTH * span;
+        let t = CURSOR_THICKNESS;
+        let color = grid.palette.cursor;
+
+        if !self.focused {
+            // Contorno de 1px
+            let w = UNDERLINE_THICKNESS;
+            self.push_quad(px, py, width, w, NO_UV, color, color, 0);
+            self.push_quad(px, py + CELL_HEIGHT - w, width, w, NO_UV, color, color, 0);
+            self.push_quad(px, py, w, CELL_HEIGHT, NO_UV, color, color, 0);
+            self.push_quad(px + width - w, py, w, CELL_HEIGHT, NO_UV, color, color, 0);
+            return;
+        }
+
+        match style.shape {
+            CursorShape::Block => {
+                self.push_quad(px, py, width, CELL_HEIGHT, NO_UV, color, color, 0);
+
+                // Glyph sob o cursor redesenhado em contraste
+                if !cell.style.attrs.contains(Attrs::HIDDEN) {
+                    let c = cell.c;
+                    let zerowidth = cell.zerowidth.clone();
+                    if c != ' ' {
+                        self.push_glyph(c, px, py, width, CURSOR_TEXT_COLOR, 0);
+                    }
+                    for &c in zerowidth.iter().flatten() {
+                        self.push_glyph(c, px, py, width, CURSOR_TEXT_COLOR, 0);
+                    }
+                }
+            }
+            CursorShape::Underline => {
+                let y = py + CELL_HEIGHT - t;
+                self.push_quad(px, y, width, t, NO_UV, color, color, 0);
+            }
+            CursorShape::Bar => {
+                self.push_quad(px, py, t, CELL_HEIGHT, NO_UV, color, color, 0);
             }
         }
+    }
 
-        // Cursor (com blink)
-        if self.cursor_visible {
-            let cx = grid.cursor_x;
-            let cy = grid.cursor_y;
-            if cx < grid.cols && cy < grid.rows {
-                let px = PADDING_X + cx as f32 * CELL_WIDTH;
-                let py = PADDING_Y + cy as f32 * CELL_HEIGHT;
-                
-                let x0 = px * scale_x - 1.0;
-                let y0 = 1.0 - py * scale_y;
-                let x1 = (px + CELL_WIDTH) * scale_x - 1.0;
-                let y1 = 1.0 - (py + CELL_HEIGHT) * scale_y;
-
-                let base = self.vertices.len() as u32;
-
-                // Cursor block elegante com a cor do tema
-                self.vertices.push(Vertex {
-                    position: [x0, y0],
-                    tex_coords: [0.0, 0.0],
-                    fg_color: CURSOR_TEXT_COLOR,
-                    bg_color: CURSOR_COLOR,
-                });
-                self.vertices.push(Vertex {
-                    position: [x1, y0],
-                    tex_coords: [0.0, 0.0],
-                    fg_color: CURSOR_TEXT_COLOR,
-                    bg_color: CURSOR_COLOR,
-                });
-                self.vertices.push(Vertex {
-                    position: [x1, y1],
-                    tex_coords: [0.0, 0.0],
-                    fg_color: CURSOR_TEXT_COLOR,
-                    bg_color: CURSOR_COLOR,
-                });
-                self.vertices.push(Vertex {
-                    position: [x0, y1],
-                    tex_coords: [0.0, 0.0],
-                    fg_color: CURSOR_TEXT_COLOR,
-                    bg_color: CURSOR_COLOR,
-                });
-
-                self.indices.extend_from_slice(&[
-                    base, base + 1, base + 2,
-                    base, base + 2, base + 3,
-                ]);
+    /// Adiciona o glyph de `c` centralizado horizontalmente em `width` pixels
+    fn push_glyph(&mut self, c: char, px: f32, py: f32, width: f32, fg: [f32; 4], flags: u32) {
+        let glyph = self.glyph_cache.get(c, &self.queue);
+        if glyph.width == 0.0 || glyph.height == 0.0 {
+            return;
+        }
+
+        let offset = ((width - glyph.advance) / 2.0).round();
+        let x = px + offset + glyph.left;
+        let y = py + glyph.top;
+
+        // Background transparente: só o glyph é misturado
+        let clear = [fg[0], fg[1], fg[2], 0.0];
+        self.push_quad(x, y, glyph.width, glyph.height, glyph.uv, fg, clear, flags);
+    }
+
+    /// Cores (foreground, background) de uma célula, com inverse e seleção
+    fn cell_colors(grid: &Grid, cell: &Cell, selected: bool) -> ([f32; 4], [f32; 4]) {
+        let (fg, bg) = if cell.style.attrs.contains(Attrs::INVERSE) != selected {
+            (cell.style.bg, cell.style.fg)
+        } else {
+            (cell.style.fg, cell.style.bg)
+        };
+        (grid.palette.resolve(fg), grid.palette.resolve(bg))
+    }
+
+    /// Faixa de imagem na linha `y` da tela
+    fn push_image(&mut self, fragment: &ImageFragment, y: usize) {
+        let Some(uv) = self.image_cache.uv(&fragment.image, fragment.source) else {
+            return;
+        };
+        let px = PADDING_X + fragment.col as f32 * CELL_WIDTH + fragment.x as f32;
+        let py = PADDING_Y + y as f32 * CELL_HEIGHT + fragment.y as f32;
+        let (width, height) = (fragment.width as f32, fragment.height as f32);
+        self.push_quad(px, py, width, height, uv, [0.0; 4], [0.0; 4], FLAG_IMAGE);
+    }
+
+    /// Linha horizontal de decoração na cor de foreground
+    fn push_line(&mut self, x: f32, y: f32, width: f32, color: [f32; 4], flags: u32) {
+        let clear = [color[0], color[1], color[2], 0.0];
+        self.push_quad(x, y, width, UNDERLINE_THICKNESS, NO_UV, color, clear, flags | FLAG_SOLID);
+    }
+
+    /// Desenha o sublinhado de uma célula com `width` pixels
+    fn push_underline(
+        &mut self,
+        style: Underline,
+        px: f32,
+        py: f32,
+        width: f32,
+        color: [f32; 4],
+        flags: u32,
+    ) {
+        let y = py + self.glyph_cache.baseline() + UNDERLINE_OFFSET;
+        let t = UNDERLINE_THICKNESS;
+        // Fase relativa à grade para padrões contínuos entre células
+        let phase = px - PADDING_X;
+
+        match style {
+            Underline::None => {}
+            Underline::Single => {
+                self.push_line(px, y, width, color, flags);
+            }
+            Underline::Double => {
+                self.push_line(px, y, width, color, flags);
+                self.push_line(px, y + t * 2.0, width, color, flags);
+            }
+            Underline::Dotted => {
+                let mut x = 0.0;
+                while x < width {
+                    if ((phase + x) as u32).is_multiple_of(2) {
+                        self.push_line(px + x, y, t, color, flags);
+                    }
+                    x += t;
+                }
+            }
+            Underline::Dashed => {
+                // Traços de 3px com intervalos de 2px
+                let mut x = 0.0;
+                while x < width {
+                    if (phase + x) as u32 % 5 < 3 {
+                        self.push_line(px + x, y, t, color, flags);
+                    }
+                    x += t;
+                }
+            }
+            Underline::Curly => {
+                // Onda senoidal com um período por célula, uma coluna por pixel
+                let mut x = 0.0;
+                while x < width {
+                    let angle = (phase + x) / CELL_WIDTH * std::f32::consts::TAU;
+                    let dy = (angle.sin() * 1.5).round();
+                    self.push_line(px + x, y + dy, t, color, flags);
+                    x += t;
+                }
             }
         }
     }
+
+    /// Adiciona um quad em coordenadas de pixel (origem no canto superior esquerdo)
+    #[allow(clippy::too_many_arguments)]
+    fn push_quad(
+        &mut self,
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+        uv: (f32, f32, f32, f32),
+        fg: [f32; 4],
+        bg: [f32; 4],
+        flags: u32,
+    ) {
+        let scale_x = 2.0 / self.size.width as f32;
+        let scale_y = 2.0 / self.size.height as f32;
+
+        // Coordenadas em clip space (-1 a 1)
+        let x0 = x * scale_x - 1.0;
+        let y0 = 1.0 - y * scale_y;
+        let x1 = (x + w) * scale_x - 1.0;
+        let y1 = 1.0 - (y + h) * scale_y;
+
+        let base = self.vertices.len() as u32;
+
+        // 4 vertices por quad
+        self.vertices.push(Vertex {
+            position: [x0, y0],
+            tex_coords: [uv.0, uv.1],
+            fg_color: fg,
+            bg_color: bg,
+            flags,
+        });
+        self.vertices.push(Vertex {
+            position: [x1, y0],
+            tex_coords: [uv.2, uv.1],
+            fg_color: fg,
+            bg_color: bg,
+            flags,
+        });
+        self.vertices.push(Vertex {
+            position: [x1, y1],
+            tex_coords: [uv.2, uv.3],
+            fg_color: fg,
+            bg_color: bg,
+            flags,
+        });
+        self.vertices.push(Vertex {
+            position: [x0, y1],
+            tex_coords: [uv.0, uv.3],
+            fg_color: fg,
+            bg_color: bg,
+            flags,
+        });
+
+        // 2 triângulos por quad
+        self.indices.extend_from_slice(&[
+            base, base + 1, base + 2,
+            base, base + 2, base + 3,
+        ]);
+    }
 }
@@ -1,11 +1,23 @@
 // Shader para renderização de células do terminal
 // Otimizado para legibilidade com Gamma Correction
 
+// Flags por vertex (espelham FLAG_* em mod.rs)
+const FLAG_DIM: u32 = 1u;
+const FLAG_BLINK: u32 = 2u;
+const FLAG_SOLID: u32 = 4u;
+const FLAG_IMAGE: u32 = 8u;
+
+struct Globals {
+    // Opacidade atual do texto piscante (SGR 5)
+    blink_alpha: f32,
+};
+
 struct VertexInput {
     @location(0) position: vec2<f32>,
     @location(1) tex_coords: vec2<f32>,
     @location(2) fg_color: vec4<f32>,
     @location(3) bg_color: vec4<f32>,
+    @location(4) flags: u32,
 };
 
 struct VertexOutput {
@@ -13,6 +25,7 @@ struct VertexOutput {
     @location(0) tex_coords: vec2<f32>,
     @location(1) fg_color: vec4<f32>,
     @location(2) bg_color: vec4<f32>,
+    @location(3) @interpolate(flat) flags: u32,
 };
 
 @vertex
@@ -22,6 +35,7 @@ fn vs_main(in: VertexInput) -> VertexOutput {
     out.tex_coords = in.tex_coords;
     out.fg_color = in.fg_color;
     out.bg_color = in.bg_color;
+    out.flags = in.flags;
     return out;
 }
 
@@ -29,9 +43,18 @@ fn vs_main(in: VertexInput) -> VertexOutput {
 var t_glyph: texture_2d<f32>;
 @group(0) @binding(1)
 var s_glyph: sampler;
+@group(0) @binding(2)
+var<uniform> globals: Globals;
+@group(0) @binding(3)
+var t_image: texture_2d<f32>;
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Imagens (sixel) usam a cor do próprio atlas
+    if ((in.flags & FLAG_IMAGE) != 0u) {
+        return textureSampleLevel(t_image, s_glyph, in.tex_coords, 0.0);
+    }
+
     // 1. Amostra do glyph (alpha map)
     var alpha = 0.0;
     if (in.tex_coords.x > 0.0 || in.tex_coords.y > 0.0) {
@@ -40,11 +63,23 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
         // Sharpening leve / Gamma correction para texto
         // Isso faz o texto parecer mais "bold" e nítido
         alpha = pow(alpha, 1.0 / 1.4); 
+    } else if ((in.flags & FLAG_SOLID) != 0u) {
+        // Decorações (sublinhado, tachado) são foreground sólido
+        alpha = 1.0;
+    }
+
+    // 2. Atributos do foreground: dim esmaece, blink anima a opacidade
+    var fg = in.fg_color;
+    if ((in.flags & FLAG_DIM) != 0u) {
+        fg.a = fg.a * 0.5;
+    }
+    if ((in.flags & FLAG_BLINK) != 0u) {
+        fg.a = fg.a * globals.blink_alpha;
     }
 
-    // 2. Mistura background e foreground
+    // 3. Mistura background e foreground
     // Mix linear: bg * (1 - alpha) + fg * alpha
-    var color = mix(in.bg_color, in.fg_color, alpha);
+    var color = mix(in.bg_color, fg, alpha);
     
     return color;
 }
@@ -1,25 +1,201 @@
-/// Parser ANSI de alta performance
-/// State machine para sequências de escape
+//! Parser ANSI de alta performance
+//! State machine para sequências de escape
 
-use crate::config::ANSI_COLORS;
-use super::grid::{Grid, CellStyle};
+use super::grid::{Grid, Attrs, CellStyle, EraseMode, Underline};
+use std::os::unix::ffi::OsStringExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use crate::config::{CELL_HEIGHT, CELL_WIDTH, CLIPBOARD_MAX_BYTES, DEFAULT_TITLE};
+use super::charset::Charset;
+use super::event::{ClipboardKind, TermEvent};
+use super::hyperlink::Hyperlink;
+use super::image::{Image, Placement};
+use super::kitty::KittyGraphics;
+use super::row::SemanticMark;
+use super::modes::{CursorShape, CursorStyle, Mode};
+use super::palette::{self, Color};
+use super::sixel::{SixelDecoder, SIXEL_MAX_SIZE, SIXEL_REGISTERS};
 
 /// Estados do parser
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum State {
     Ground,
     Escape,
+    EscapeIntermediate,
     Csi,
     CsiParam,
     Osc,
+    /// ESC dentro de um OSC: pode ser o início do ST
+    OscEscape,
+    /// Logo após `ESC P`
+    DcsEntry,
+    /// Parâmetros e intermediários do DCS
+    DcsParam,
+    /// Payload do DCS até o ST
+    DcsPassthrough,
+    /// ESC dentro do payload: pode ser o início do ST
+    DcsEscape,
+    /// DCS malformado, SOS ou PM: descartado até o ST
+    DcsIgnore,
+    /// APC (`ESC _`), usado pelo kitty graphics protocol
+    Apc,
+    /// ESC dentro de um APC: pode ser o início do ST
+    ApcEscape,
+}
+
+/// Tamanho máximo do payload de um DCS
+const DCS_MAX_LEN: usize = 1 << 20;
+
+/// Tamanho máximo de um APC (uploads grandes chegam em partes de 4 KiB)
+const APC_MAX_LEN: usize = 1 << 20;
+
+/// Capacidades respondidas por XTGETTCAP (nome terminfo, valor)
+/// `None` é uma capacidade booleana
+const TERMCAPS: &[(&str, Option<&str>)] = &[
+    ("TN", Some("xterm-256color")),
+    ("Co", Some("256")),
+    ("colors", Some("256")),
+    ("RGB", None),
+    ("Tc", None),
+    ("setrgbf", Some("\\E[38:2:%p1%d:%p2%d:%p3%dm")),
+    ("setrgbb", Some("\\E[48:2:%p1%d:%p2%d:%p3%dm")),
+    ("Smulx", Some("\\E[4:%p1%dm")),
+    (
+        "Setulc",
+        Some("\\E[58:2::%p1%{65536}%/%d:%p1%{256}%/%{255}%&%d:%p1%{255}%&%d%;m"),
+    ),
+];
+
+/// Tamanho máximo do payload de um OSC; o excedente descarta a sequência
+const OSC_MAX_LEN: usize = 1 << 20;
+
+/// Profundidade máxima da pilha de títulos (CSI 22 t)
+const TITLE_STACK_MAX: usize = 10;
+
+/// Resultado de um passo do decoder UTF-8
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Utf8Step {
+    /// Sequência ainda incompleta
+    Pending,
+    /// Caractere completo
+    Char(char),
+    /// Byte inválido (vira U+FFFD)
+    Invalid,
+    /// Sequência interrompida: emite U+FFFD e reprocessa o byte
+    Interrupted,
+}
+
+/// Decoder UTF-8 incremental
+/// Mantém sequências parciais entre chamadas de `process`, já que o PTY
+/// entrega a saída em blocos de tamanho fixo
+#[derive(Debug, Clone, Copy)]
+struct Utf8Decoder {
+    codepoint: u32,
+    remaining: u8,
+    /// Faixa válida para o próximo byte de continuação
+    lower: u8,
+    upper: u8,
+}
+
+impl Utf8Decoder {
+    const fn new() -> Self {
+        Self {
+            codepoint: 0,
+            remaining: 0,
+            lower: 0x80,
+            upper: 0xbf,
+        }
+    }
+
+    fn is_pending(&self) -> bool {
+        self.remaining > 0
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Alimenta um byte >= 0x80
+    /// Rejeita overlongs, surrogates e valores acima de U+10FFFF já no
+    /// segundo byte, substituindo cada subparte máxima inválida por U+FFFD
+    fn advance(&mut self, byte: u8) -> Utf8Step {
+        if self.remaining == 0 {
+            let (codepoint, remaining, lower, upper) = match byte {
+                0xc2..=0xdf => (byte & 0x1f, 1, 0x80, 0xbf),
+                0xe0 => (byte & 0x0f, 2, 0xa0, 0xbf),
+                0xe1..=0xec | 0xee..=0xef => (byte & 0x0f, 2, 0x80, 0xbf),
+                0xed => (byte & 0x0f, 2, 0x80, 0x9f),
+                0xf0 => (byte & 0x07, 3, 0x90, 0xbf),
+                0xf1..=0xf3 => (byte & 0x07, 3, 0x80, 0xbf),
+                0xf4 => (byte & 0x07, 3, 0x80, 0x8f),
+                _ => return Utf8Step::Invalid,
+            };
+            self.codepoint = codepoint as u32;
+            self.remaining = remaining;
+            self.lower = lower;
+            self.upper = upper;
+            return Utf8Step::Pending;
+        }
+
+        if byte < self.lower || byte > self.upper {
+            self.reset();
+            return Utf8Step::Interrupted;
+        }
+
+        self.codepoint = (self.codepoint << 6) | (byte & 0x3f) as u32;
+        self.remaining -= 1;
+        self.lower = 0x80;
+        self.upper = 0xbf;
+
+        if self.remaining > 0 {
+            return Utf8Step::Pending;
+        }
+
+        let c = char::from_u32(self.codepoint).unwrap_or(char::REPLACEMENT_CHARACTER);
+        self.reset();
+        Utf8Step::Char(c)
+    }
 }
 
 /// Parser ANSI
 pub struct AnsiParser {
     state: State,
     params: Vec<u16>,
+    /// Marca os parâmetros introduzidos por ':' (sub-parâmetros ITU T.416)
+    subparams: Vec<bool>,
     current_param: u16,
+    current_is_sub: bool,
     intermediate: Vec<u8>,
+    utf8: Utf8Decoder,
+    /// Último caractere impresso, repetido por REP
+    last_char: Option<char>,
+    /// Single shift pendente (SS2/SS3) para o próximo caractere
+    single_shift: Option<usize>,
+    /// Byte final e payload do DCS em andamento
+    dcs_final: u8,
+    dcs_data: Vec<u8>,
+    /// Imagem sixel em decodificação, alimentada direto pelo payload
+    sixel: Option<SixelDecoder>,
+    /// Payload do APC em andamento e se excedeu `APC_MAX_LEN`
+    apc_data: Vec<u8>,
+    apc_overflow: bool,
+    /// Imagens do kitty graphics protocol
+    kitty: KittyGraphics,
+    /// Payload do OSC em andamento
+    osc_data: Vec<u8>,
+    /// Payload excedeu `OSC_MAX_LEN`
+    osc_overflow: bool,
+    /// Título atual e pilha de títulos salvos
+    title: Option<String>,
+    title_stack: Vec<Option<String>>,
+    /// Diretório atual informado por OSC 7
+    cwd: Option<PathBuf>,
+    /// Respostas para o PTY (DA, DSR, CPR...)
+    output: Vec<u8>,
+    /// Eventos para a janela
+    events: Vec<TermEvent>,
 }
 
 impl AnsiParser {
@@ -27,11 +203,34 @@ impl AnsiParser {
         Self {
             state: State::Ground,
             params: Vec::with_capacity(16),
+            subparams: Vec::with_capacity(16),
             current_param: 0,
+            current_is_sub: false,
             intermediate: Vec::with_capacity(8),
+            utf8: Utf8Decoder::new(),
+            last_char: None,
+            single_shift: None,
+            dcs_final: 0,
+            dcs_data: Vec::new(),
+            sixel: None,
+            apc_data: Vec::new(),
+            apc_overflow: false,
+            kitty: KittyGraphics::default(),
+            osc_data: Vec::new(),
+            osc_overflow: false,
+            title: None,
+            title_stack: Vec::new(),
+            cwd: None,
+            output: Vec::new(),
+            events: Vec::new(),
         }
     }
 
+    /// Diretório atual informado pelo shell (OSC 7), se houver
+    pub fn cwd(&self) -> Option<&Path> {
+        self.cwd.as_deref()
+    }
+
     /// Processa bytes e atualiza o grid
     pub fn process(&mut self, data: &[u8], grid: &mut Grid) {
         for &byte in data {
@@ -39,53 +238,128 @@ impl AnsiParser {
         }
     }
 
+    /// Retira as respostas pendentes para escrever no PTY
+    pub fn take_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.output)
+    }
+
+    /// Retira os eventos pendentes para a janela
+    pub fn take_events(&mut self) -> Vec<TermEvent> {
+        std::mem::take(&mut self.events)
+    }
+
     fn process_byte(&mut self, byte: u8, grid: &mut Grid) {
         match self.state {
             State::Ground => self.ground(byte, grid),
             State::Escape => self.escape(byte, grid),
+            State::EscapeIntermediate => self.escape_intermediate(byte, grid),
             State::Csi | State::CsiParam => self.csi(byte, grid),
             State::Osc => self.osc(byte, grid),
+            State::OscEscape => self.osc_escape(byte, grid),
+            State::DcsEntry | State::DcsParam => self.dcs_param(byte),
+            State::DcsPassthrough => self.dcs_passthrough(byte),
+            State::DcsEscape => self.dcs_escape(byte, grid),
+            State::Apc => self.apc(byte),
+            State::ApcEscape => self.apc_escape(byte, grid),
+            State::DcsIgnore => match byte {
+                0x1b => self.state = State::Escape,
+                0x18 | 0x1a => self.reset(),
+                _ => {}
+            },
         }
     }
 
     fn ground(&mut self, byte: u8, grid: &mut Grid) {
+        // Byte ASCII no meio de uma sequência multibyte a interrompe
+        if byte < 0x80 && self.utf8.is_pending() {
+            self.utf8.reset();
+            grid.write_char(char::REPLACEMENT_CHARACTER);
+        }
+
         match byte {
             0x1b => self.state = State::Escape,
             0x07 => {} // Bell - ignorar
             0x08 => grid.backspace(),
-            0x09 => grid.tab(),
-            0x0a | 0x0b | 0x0c => grid.newline(),
+            0x09 => grid.tab(1),
+            0x0a..=0x0c => grid.linefeed(),
             0x0d => grid.carriage_return(),
-            0x20..=0x7e => grid.write_char(byte as char),
-            0xc0..=0xff => {
-                // UTF-8 multibyte - simplificado, renderiza como ?
-                // TODO: implementar decode UTF-8 completo
-                grid.write_char('?');
-            }
+            0x0e => grid.charsets.lock_shift(1), // SO - G1 em GL
+            0x0f => grid.charsets.lock_shift(0), // SI - G0 em GL
+            0x20..=0x7e => self.print(byte as char, grid),
+            0x80..=0xff => self.utf8_byte(byte, grid),
             _ => {} // Ignora outros controles
         }
     }
 
+    /// Decodifica bytes UTF-8 multibyte
+    fn utf8_byte(&mut self, byte: u8, grid: &mut Grid) {
+        match self.utf8.advance(byte) {
+            Utf8Step::Pending => {}
+            Utf8Step::Char(c) => self.print(c, grid),
+            Utf8Step::Invalid => grid.write_char(char::REPLACEMENT_CHARACTER),
+            Utf8Step::Interrupted => {
+                grid.write_char(char::REPLACEMENT_CHARACTER);
+                self.utf8_byte(byte, grid);
+            }
+        }
+    }
+
+    /// Escreve um caractere imprimível no grid, traduzido pelo charset ativo
+    fn print(&mut self, c: char, grid: &mut Grid) {
+        let c = grid.charsets.map(c, self.single_shift.take());
+        grid.write_char(c);
+        self.last_char = Some(c);
+    }
+
     fn escape(&mut self, byte: u8, grid: &mut Grid) {
         match byte {
             b'[' => {
+                self.reset();
                 self.state = State::Csi;
-                self.params.clear();
-                self.current_param = 0;
-                self.intermediate.clear();
+            }
+            b'P' => {
+                // DCS
+                self.reset();
+                self.state = State::DcsEntry;
             }
             b']' => {
+                self.osc_data.clear();
+                self.osc_overflow = false;
                 self.state = State::Osc;
             }
+            b'_' => {
+                self.apc_data.clear();
+                self.apc_overflow = false;
+                self.state = State::Apc;
+            }
+            b'X' | b'^' => {
+                // SOS / PM - ignorados até o ST
+                self.state = State::DcsIgnore;
+            }
             b'c' => {
                 // Reset terminal
-                grid.clear();
-                grid.current_style = CellStyle::default();
+                grid.reset();
+                self.kitty = KittyGraphics::default();
+                self.state = State::Ground;
+            }
+            b'7' => {
+                // DECSC - Save cursor
+                grid.save_cursor();
+                self.state = State::Ground;
+            }
+            b'8' => {
+                // DECRC - Restore cursor
+                grid.restore_cursor();
                 self.state = State::Ground;
             }
             b'D' => {
                 // Index - move cursor down
-                grid.newline();
+                grid.index();
+                self.state = State::Ground;
+            }
+            b'H' => {
+                // HTS - Tab stop na coluna atual
+                grid.set_tab_stop();
                 self.state = State::Ground;
             }
             b'E' => {
@@ -95,189 +369,936 @@ impl AnsiParser {
                 self.state = State::Ground;
             }
             b'M' => {
-                // Reverse index - move cursor up
-                if grid.cursor_y > 0 {
-                    grid.cursor_y -= 1;
-                }
+                // Reverse index - move cursor up, rolando na margem superior
+                grid.reverse_index();
                 self.state = State::Ground;
             }
+            b'N' | b'O' => {
+                // SS2 / SS3 - G2 ou G3 só para o próximo caractere
+                self.single_shift = Some(if byte == b'N' { 2 } else { 3 });
+                self.state = State::Ground;
+            }
+            b'n' | b'o' => {
+                // LS2 / LS3 - Invoca G2 ou G3 em GL
+                grid.charsets.lock_shift(if byte == b'n' { 2 } else { 3 });
+                self.state = State::Ground;
+            }
+            0x20..=0x2f => {
+                // Intermediário (designação de charset, etc.)
+                self.reset();
+                self.intermediate.push(byte);
+                self.state = State::EscapeIntermediate;
+            }
             _ => self.state = State::Ground,
         }
     }
 
+    fn escape_intermediate(&mut self, byte: u8, grid: &mut Grid) {
+        match byte {
+            0x20..=0x2f => self.intermediate.push(byte),
+            0x30..=0x7e => {
+                // Designação de charset: ESC ( ) * + seleciona G0–G3
+                let slot = match self.intermediate.as_slice() {
+                    b"(" => Some(0),
+                    b")" => Some(1),
+                    b"*" => Some(2),
+                    b"+" => Some(3),
+                    _ => None,
+                };
+                if let (Some(slot), Some(charset)) = (slot, Charset::from_designator(byte)) {
+                    grid.charsets.designate(slot, charset);
+                }
+                self.reset();
+            }
+            _ => self.reset(),
+        }
+    }
+
     fn csi(&mut self, byte: u8, grid: &mut Grid) {
         match byte {
             b'0'..=b'9' => {
                 self.state = State::CsiParam;
-                self.current_param = self.current_param * 10 + (byte - b'0') as u16;
+                self.current_param = self
+                    .current_param
+                    .saturating_mul(10)
+                    .saturating_add((byte - b'0') as u16);
             }
-            b';' => {
-                self.params.push(self.current_param);
-                self.current_param = 0;
+            b';' | b':' => {
+                self.push_param();
+                self.current_is_sub = byte == b':';
             }
-            b'?' | b'>' | b'!' => {
+            // Marcadores privados e intermediários
+            b'?' | b'>' | b'<' | b'=' | 0x20..=0x2f => {
                 self.intermediate.push(byte);
             }
             // Final bytes
-            b'A' => {
+            0x40..=0x7e => {
+                self.push_param();
+                self.csi_dispatch(byte, grid);
+                self.reset();
+            }
+            _ => {
+                self.reset();
+            }
+        }
+    }
+
+    fn csi_dispatch(&mut self, byte: u8, grid: &mut Grid) {
+        match byte {
+            b'A' if self.intermediate.is_empty() => {
                 // Cursor up
                 let n = self.get_param(0, 1) as isize;
                 grid.move_cursor_relative(0, -n);
-                self.reset();
             }
-            b'B' => {
+            b'B' if self.intermediate.is_empty() => {
                 // Cursor down
                 let n = self.get_param(0, 1) as isize;
                 grid.move_cursor_relative(0, n);
-                self.reset();
             }
-            b'C' => {
+            b'C' if self.intermediate.is_empty() => {
                 // Cursor forward
                 let n = self.get_param(0, 1) as isize;
                 grid.move_cursor_relative(n, 0);
-                self.reset();
             }
-            b'D' => {
+            b'D' if self.intermediate.is_empty() => {
                 // Cursor back
                 let n = self.get_param(0, 1) as isize;
                 grid.move_cursor_relative(-n, 0);
-                self.reset();
             }
-            b'H' | b'f' => {
+            b'H' | b'f' if self.intermediate.is_empty() => {
                 // Cursor position
-                self.params.push(self.current_param);
                 let row = self.get_param(0, 1).saturating_sub(1) as usize;
                 let col = self.get_param(1, 1).saturating_sub(1) as usize;
+                grid.goto(col, row);
+            }
+            b'G' | b'`' if self.intermediate.is_empty() => {
+                // Cursor horizontal absolute
+                let col = self.get_param(0, 1).saturating_sub(1) as usize;
+                let row = grid.cursor_y;
                 grid.move_cursor(col, row);
-                self.reset();
+            }
+            b'd' if self.intermediate.is_empty() => {
+                // Line position absolute
+                let row = self.get_param(0, 1).saturating_sub(1) as usize;
+                grid.goto_line(row);
             }
             b'J' => {
-                // Erase in display
-                self.params.push(self.current_param);
-                match self.get_param(0, 0) {
-                    0 => grid.clear_to_end_of_screen(),
-                    1 => {} // TODO: clear from start
-                    2 | 3 => grid.clear(),
+                // Erase in display (ED / DECSED)
+                let selective = match self.intermediate.as_slice() {
+                    [] => false,
+                    b"?" => true,
+                    _ => return,
+                };
+                let mode = match self.get_param(0, 0) {
+                    0 => EraseMode::ToEnd,
+                    1 => EraseMode::ToStart,
+                    2 => EraseMode::All,
+                    3 => EraseMode::Scrollback,
+                    _ => return,
+                };
+                grid.erase_in_display(mode, selective);
+            }
+            b'K' => {
+                // Erase in line (EL / DECSEL)
+                let selective = match self.intermediate.as_slice() {
+                    [] => false,
+                    b"?" => true,
+                    _ => return,
+                };
+                let mode = match self.get_param(0, 0) {
+                    0 => EraseMode::ToEnd,
+                    1 => EraseMode::ToStart,
+                    2 => EraseMode::All,
+                    _ => return,
+                };
+                grid.erase_in_line(mode, selective);
+            }
+            b'q' if self.intermediate.as_slice() == b"\"" => {
+                // DECSCA - Atributo de proteção para DECSED/DECSEL
+                let protected = self.get_param(0, 0) == 1;
+                grid.current_style.attrs.set(Attrs::PROTECTED, protected);
+            }
+            b'q' if self.intermediate.as_slice() == b" " => {
+                // DECSCUSR - Forma do cursor
+                if let Some(style) = CursorStyle::from_decscusr(self.get_param(0, 0)) {
+                    grid.modes.cursor_style = style;
+                }
+            }
+            b'@' if self.intermediate.is_empty() => {
+                // ICH - Insert characters
+                grid.insert_blank(self.get_param(0, 1) as usize);
+            }
+            b'P' if self.intermediate.is_empty() => {
+                // DCH - Delete characters
+                grid.delete_chars(self.get_param(0, 1) as usize);
+            }
+            b'X' if self.intermediate.is_empty() => {
+                // ECH - Erase characters
+                grid.erase_chars(self.get_param(0, 1) as usize);
+            }
+            b'L' if self.intermediate.is_empty() => {
+                // IL - Insert lines
+                grid.insert_lines(self.get_param(0, 1) as usize);
+            }
+            b'M' if self.intermediate.is_empty() => {
+                // DL - Delete lines
+                grid.delete_lines(self.get_param(0, 1) as usize);
+            }
+            b'S' if self.intermediate == b"?" => {
+                // XTSMGRAPHICS - Só leitura (Pa 1) e máximo (Pa 4)
+                let reply = match (self.get_param(0, 0), self.get_param(1, 0)) {
+                    (1, 1 | 4) => format!("\x1b[?1;0;{}S", SIXEL_REGISTERS),
+                    (2, 1 | 4) => format!("\x1b[?2;0;{};{}S", SIXEL_MAX_SIZE, SIXEL_MAX_SIZE),
+                    (item, _) => format!("\x1b[?{};2S", item),
+                };
+                self.output.extend_from_slice(reply.as_bytes());
+            }
+            b'S' if self.intermediate.is_empty() => {
+                // SU - Scroll up
+                grid.scroll_up(self.get_param(0, 1) as usize);
+            }
+            b'T' if self.intermediate.is_empty() && self.params.len() <= 1 => {
+                // SD - Scroll down
+                grid.scroll_down(self.get_param(0, 1) as usize);
+            }
+            b'I' if self.intermediate.is_empty() => {
+                // CHT - Avança n tab stops
+                grid.tab(self.get_param(0, 1) as usize);
+            }
+            b'Z' if self.intermediate.is_empty() => {
+                // CBT - Volta n tab stops
+                grid.back_tab(self.get_param(0, 1) as usize);
+            }
+            b'g' if self.intermediate.is_empty() => {
+                // TBC - 0 limpa a tab stop atual, 3 limpa todas
+                match self.params.first().copied().unwrap_or(0) {
+                    0 => grid.clear_tab_stops(false),
+                    3 => grid.clear_tab_stops(true),
                     _ => {}
                 }
-                self.reset();
             }
-            b'K' => {
-                // Erase in line
-                self.params.push(self.current_param);
-                match self.get_param(0, 0) {
-                    0 => grid.clear_to_end_of_line(),
-                    1 => {} // TODO: clear from start
-                    2 => {
-                        let y = grid.cursor_y;
-                        grid.clear_line(y);
+            b't' if self.intermediate.is_empty() => {
+                // Operações de janela (XTWINOPS): tamanhos em pixels e pilha de títulos
+                // 22/23 com Ps 0 ou 2 salvam/restauram o título
+                match (self.get_param(0, 0), self.get_param(1, 0)) {
+                    (14, _) => {
+                        // Área de texto em pixels
+                        let reply = format!(
+                            "\x1b[4;{};{}t",
+                            grid.rows * CELL_HEIGHT as usize,
+                            grid.cols * CELL_WIDTH as usize,
+                        );
+                        self.output.extend_from_slice(reply.as_bytes());
                     }
+                    (16, _) => {
+                        // Célula em pixels
+                        let reply = format!("\x1b[6;{};{}t", CELL_HEIGHT as usize, CELL_WIDTH as usize);
+                        self.output.extend_from_slice(reply.as_bytes());
+                    }
+                    (22, 0 | 2) => self.push_title(),
+                    (23, 0 | 2) => self.pop_title(),
                     _ => {}
                 }
-                self.reset();
             }
-            b'm' => {
+            b'b' if self.intermediate.is_empty() => {
+                // REP - Repete o último caractere impresso
+                if let Some(c) = self.last_char {
+                    for _ in 0..self.get_param(0, 1) {
+                        grid.write_char(c);
+                    }
+                }
+            }
+            b's' if self.intermediate.is_empty() => {
+                // SCOSC - Save cursor (mesmo registro de DECSC)
+                grid.save_cursor();
+            }
+            b'u' if self.intermediate.is_empty() => {
+                // SCORC - Restore cursor
+                grid.restore_cursor();
+            }
+            b'm' if self.intermediate.is_empty() => {
                 // SGR - Set Graphics Rendition
-                self.params.push(self.current_param);
+                // Com marcador privado é outra sequência (ex.: `CSI > 4 ; 2 m`, XTMODKEYS)
                 self.process_sgr(grid);
-                self.reset();
             }
-            b'r' => {
-                // Set scrolling region - ignorar por enquanto
-                self.reset();
+            b'r' if self.intermediate.is_empty() => {
+                // DECSTBM - Set scrolling region
+                let top = self.get_param(0, 1).saturating_sub(1) as usize;
+                let bottom = self.get_param(1, grid.rows as u16).saturating_sub(1) as usize;
+                grid.set_scroll_region(top, bottom);
             }
             b'h' | b'l' => {
-                // Set/reset mode - ignorar por enquanto
-                self.reset();
+                // Set/reset mode (SM/RM e DECSET/DECRST)
+                let enable = byte == b'h';
+                let private = match self.intermediate.as_slice() {
+                    [] => false,
+                    b"?" => true,
+                    _ => return,
+                };
+                for &n in &self.params {
+                    let mode = if private { Mode::from_dec(n) } else { Mode::from_ansi(n) };
+                    match mode {
+                        Some(mode) => grid.set_mode(mode, enable),
+                        None => log::debug!("Modo não suportado: {}{}", if private { "?" } else { "" }, n),
+                    }
+                }
             }
-            b'c' => {
-                // Device attributes - ignorar
-                self.reset();
+            b'c' if self.get_param(0, 0) == 0 => {
+                // Device attributes
+                match self.intermediate.as_slice() {
+                    // Primary DA: VT220 com sixel e cores ANSI
+                    [] => self.output.extend_from_slice(b"\x1b[?62;4;22c"),
+                    // Secondary DA: tipo 0, versão do RTerm, ROM 0
+                    b">" => {
+                        let reply = format!("\x1b[>0;{};0c", Self::version_number());
+                        self.output.extend_from_slice(reply.as_bytes());
+                    }
+                    _ => {}
+                }
             }
             b'n' => {
-                // Device status report - ignorar
-                self.reset();
+                // Device status report
+                let private = match self.intermediate.as_slice() {
+                    [] => false,
+                    b"?" => true,
+                    _ => return,
+                };
+                match self.get_param(0, 0) {
+                    // Operating status: OK
+                    5 if !private => self.output.extend_from_slice(b"\x1b[0n"),
+                    // Cursor position report (CPR / DECXCPR)
+                    6 => {
+                        let (col, row) = grid.cursor_report_position();
+                        let marker = if private { "?" } else { "" };
+                        let reply = format!("\x1b[{}{};{}R", marker, row + 1, col + 1);
+                        self.output.extend_from_slice(reply.as_bytes());
+                    }
+                    _ => {}
+                }
             }
+            _ => {}
+        }
+    }
+
+    fn dcs_param(&mut self, byte: u8) {
+        match byte {
+            b'0'..=b'9' => {
+                self.state = State::DcsParam;
+                self.current_param = self
+                    .current_param
+                    .saturating_mul(10)
+                    .saturating_add((byte - b'0') as u16);
+            }
+            b';' | b':' => {
+                self.state = State::DcsParam;
+                self.push_param();
+                self.current_is_sub = byte == b':';
+            }
+            // Marcador privado só logo após `ESC P`
+            b'?' | b'>' | b'<' | b'=' if self.state == State::DcsEntry => {
+                self.state = State::DcsParam;
+                self.intermediate.push(byte);
+            }
+            0x20..=0x2f => {
+                self.state = State::DcsParam;
+                self.intermediate.push(byte);
+            }
+            // Final: o resto até o ST é payload
+            0x40..=0x7e => {
+                self.push_param();
+                self.dcs_final = byte;
+                self.dcs_data.clear();
+                if byte == b'q' && self.intermediate.is_empty() {
+                    self.sixel = Some(SixelDecoder::new(&self.params));
+                }
+                self.state = State::DcsPassthrough;
+            }
+            0x18 | 0x1a => self.reset(),
+            0x1b => self.state = State::Escape,
+            0x00..=0x1f => {} // Controles são ignorados
+            _ => self.state = State::DcsIgnore,
+        }
+    }
+
+    fn dcs_passthrough(&mut self, byte: u8) {
+        match byte {
+            0x1b => self.state = State::DcsEscape,
+            0x18 | 0x1a => self.reset(),
             _ => {
-                self.reset();
+                if let Some(sixel) = &mut self.sixel {
+                    sixel.feed(byte);
+                } else if self.dcs_data.len() < DCS_MAX_LEN {
+                    self.dcs_data.push(byte);
+                } else {
+                    log::debug!("DCS excedeu {} bytes, descartado", DCS_MAX_LEN);
+                    self.state = State::DcsIgnore;
+                }
             }
         }
     }
 
-    fn osc(&mut self, byte: u8, _grid: &mut Grid) {
+    fn dcs_escape(&mut self, byte: u8, grid: &mut Grid) {
+        if byte == b'\\' {
+            // ST (ESC \)
+            self.dcs_dispatch(grid);
+            self.reset();
+        } else {
+            // ESC sem '\' aborta o DCS e inicia outra sequência
+            self.reset();
+            self.state = State::Escape;
+            self.escape(byte, grid);
+        }
+    }
+
+    /// Executa o DCS acumulado em `dcs_data`
+    fn dcs_dispatch(&mut self, grid: &mut Grid) {
+        let data = std::mem::take(&mut self.dcs_data);
+        match (self.intermediate.as_slice(), self.dcs_final) {
+            (b"", b'q') => {
+                // Sixel
+                let image = self.sixel.take().and_then(|sixel| sixel.finish(grid.palette.background));
+                if let Some((width, height, data)) = image {
+                    let col = grid.cursor_x;
+                    let image = Arc::new(Image::new(width, height, data));
+                    grid.place_image(&image, &Placement::natural(&image), true);
+                    // Cursor na linha abaixo da imagem, na coluna inicial
+                    grid.index();
+                    grid.cursor_x = col;
+                }
+            }
+            (b"$", b'q') => self.decrqss(&data, grid),
+            (b"+", b'q') => self.xtgettcap(&data),
+            (intermediate, final_byte) => log::debug!(
+                "DCS não suportado: {}{}",
+                String::from_utf8_lossy(intermediate),
+                final_byte as char,
+            ),
+        }
+    }
+
+    /// DECRQSS - Responde com a sequência que reproduz a configuração pedida
+    fn decrqss(&mut self, setting: &[u8], grid: &Grid) {
+        let report = match setting {
+            b"m" => Some(format!("{}m", Self::sgr_report(&grid.current_style))),
+            b"r" => {
+                let (top, bottom) = grid.scroll_region();
+                Some(format!("{};{}r", top + 1, bottom + 1))
+            }
+            b" q" => {
+                let style = grid.modes.cursor_style;
+                let base = match style.shape {
+                    CursorShape::Block => 1,
+                    CursorShape::Underline => 3,
+                    CursorShape::Bar => 5,
+                };
+                Some(format!("{} q", base + u8::from(!style.blinking)))
+            }
+            _ => None,
+        };
+
+        // 1 = válido, 0 = configuração desconhecida (convenção do xterm)
+        let reply = match report {
+            Some(report) => format!("\x1bP1$r{}\x1b\\", report),
+            None => "\x1bP0$r\x1b\\".to_string(),
+        };
+        self.output.extend_from_slice(reply.as_bytes());
+    }
+
+    /// Parâmetros SGR que reproduzem `style`, começando por um reset
+    fn sgr_report(style: &CellStyle) -> String {
+        let mut params = vec!["0".to_string()];
+        let attrs = [
+            (Attrs::BOLD, "1"),
+            (Attrs::DIM, "2"),
+            (Attrs::ITALIC, "3"),
+            (Attrs::BLINK, "5"),
+            (Attrs::INVERSE, "7"),
+            (Attrs::HIDDEN, "8"),
+            (Attrs::STRIKETHROUGH, "9"),
+            (Attrs::OVERLINE, "53"),
+        ];
+        for (attr, code) in attrs {
+            if style.attrs.contains(attr) {
+                params.push(code.to_string());
+            }
+        }
+
+        match style.underline {
+            Underline::None => {}
+            Underline::Single => params.push("4".to_string()),
+            Underline::Double => params.push("4:2".to_string()),
+            Underline::Curly => params.push("4:3".to_string()),
+            Underline::Dotted => params.push("4:4".to_string()),
+            Underline::Dashed => params.push("4:5".to_string()),
+        }
+
+        params.extend(Self::sgr_color(style.fg, 30, 38));
+        params.extend(Self::sgr_color(style.bg, 40, 48));
+        params.extend(style.underline_color.and_then(|color| Self::sgr_color(color, 0, 58)));
+        params.join(";")
+    }
+
+    /// Parâmetro SGR de uma cor; `base` 0 não tem forma curta (SGR 58)
+    fn sgr_color(color: Color, base: u16, extended: u16) -> Option<String> {
+        match color {
+            Color::Foreground | Color::Background => None,
+            Color::Indexed(n) if base > 0 && n < 8 => Some((base + n as u16).to_string()),
+            Color::Indexed(n) if base > 0 && n < 16 => Some((base + 60 + n as u16 - 8).to_string()),
+            Color::Indexed(n) => Some(format!("{};5;{}", extended, n)),
+            Color::Rgb(r, g, b) => Some(format!("{};2;{};{};{}", extended, r, g, b)),
+        }
+    }
+
+    /// XTGETTCAP - Consulta capacidades terminfo por nome em hexadecimal
+    fn xtgettcap(&mut self, data: &[u8]) {
+        for hex_name in data.split(|&b| b == b';') {
+            let name = hex_decode(hex_name);
+            let cap = name
+                .as_deref()
+                .and_then(|name| TERMCAPS.iter().find(|(cap, _)| cap.as_bytes() == name));
+
+            let reply = match cap {
+                Some((_, Some(value))) => format!(
+                    "\x1bP1+r{}={}\x1b\\",
+                    String::from_utf8_lossy(hex_name),
+                    hex_encode(value.as_bytes()),
+                ),
+                Some((_, None)) => format!("\x1bP1+r{}\x1b\\", String::from_utf8_lossy(hex_name)),
+                None => format!("\x1bP0+r{}\x1b\\", String::from_utf8_lossy(hex_name)),
+            };
+            self.output.extend_from_slice(reply.as_bytes());
+        }
+    }
+
+    fn apc(&mut self, byte: u8) {
+        match byte {
+            0x1b => self.state = State::ApcEscape,
+            0x18 | 0x1a => self.state = State::Ground,
+            0x00..=0x1f => {} // Controles são ignorados
+            _ => {
+                if self.apc_data.len() < APC_MAX_LEN {
+                    self.apc_data.push(byte);
+                } else {
+                    self.apc_overflow = true;
+                }
+            }
+        }
+    }
+
+    fn apc_escape(&mut self, byte: u8, grid: &mut Grid) {
+        if byte == b'\\' {
+            // ST (ESC \)
+            self.apc_dispatch(grid);
+            self.state = State::Ground;
+        } else {
+            // ESC sem '\' aborta o APC e inicia outra sequência
+            self.state = State::Escape;
+            self.escape(byte, grid);
+        }
+    }
+
+    /// Executa o APC acumulado em `apc_data`
+    fn apc_dispatch(&mut self, grid: &mut Grid) {
+        let data = std::mem::take(&mut self.apc_data);
+        if std::mem::take(&mut self.apc_overflow) {
+            log::debug!("APC excedeu {} bytes, descartado", APC_MAX_LEN);
+            return;
+        }
+        match data.split_first() {
+            Some((b'G', command)) => {
+                if let Some(reply) = self.kitty.command(command, grid) {
+                    self.output.extend_from_slice(&reply);
+                }
+            }
+            _ => log::debug!("APC não suportado: {}", String::from_utf8_lossy(&data[..data.len().min(32)])),
+        }
+    }
+
+    fn osc(&mut self, byte: u8, grid: &mut Grid) {
         match byte {
-            0x07 | 0x1b => {
-                // OSC terminator - ignorar o conteúdo por enquanto
+            0x07 => {
+                // BEL termina o OSC (forma xterm)
+                self.osc_dispatch(grid, b"\x07");
+                self.state = State::Ground;
+            }
+            0x1b => self.state = State::OscEscape,
+            0x18 | 0x1a => {
+                // CAN / SUB cancelam a sequência
                 self.state = State::Ground;
             }
-            _ => {} // Acumular mas ignorar
+            0x00..=0x1f => {} // Outros controles são ignorados
+            _ => {
+                if self.osc_data.len() < OSC_MAX_LEN {
+                    self.osc_data.push(byte);
+                } else {
+                    self.osc_overflow = true;
+                }
+            }
+        }
+    }
+
+    fn osc_escape(&mut self, byte: u8, grid: &mut Grid) {
+        if byte == b'\\' {
+            // ST (ESC \)
+            self.osc_dispatch(grid, b"\x1b\\");
+            self.state = State::Ground;
+        } else {
+            // ESC sem '\' aborta o OSC e inicia outra sequência
+            self.state = State::Escape;
+            self.escape(byte, grid);
+        }
+    }
+
+    /// Executa o OSC acumulado em `osc_data`
+    /// Respostas usam o mesmo terminador (`BEL` ou `ST`) da consulta
+    fn osc_dispatch(&mut self, grid: &mut Grid, terminator: &'static [u8]) {
+        let data = std::mem::take(&mut self.osc_data);
+        if std::mem::take(&mut self.osc_overflow) {
+            log::debug!("OSC excedeu {} bytes, descartado", OSC_MAX_LEN);
+            return;
+        }
+
+        let (command, payload) = match data.iter().position(|&b| b == b';') {
+            Some(i) => (&data[..i], &data[i + 1..]),
+            None => (&data[..], &[][..]),
+        };
+
+        match command {
+            b"0" | b"2" => {
+                // Título da janela (0 também define o nome do ícone)
+                let title = String::from_utf8_lossy(payload).into_owned();
+                self.set_title((!title.is_empty()).then_some(title));
+            }
+            b"1" => {} // Nome do ícone - sem equivalente
+            b"4" => self.osc_palette(payload, grid, terminator),
+            b"7" => self.osc_working_directory(payload),
+            b"8" => Self::osc_hyperlink(payload, grid),
+            b"9" => self.osc_notify(payload),
+            b"10" => self.osc_dynamic_colors(10, payload, grid, terminator),
+            b"11" => self.osc_dynamic_colors(11, payload, grid, terminator),
+            b"12" => self.osc_dynamic_colors(12, payload, grid, terminator),
+            b"52" => self.osc_clipboard(payload, terminator),
+            b"133" => Self::osc_semantic_prompt(payload, grid),
+            b"777" => self.osc_notify_rxvt(payload),
+            b"104" => {
+                // Sem parâmetros restaura a paleta inteira
+                if payload.is_empty() {
+                    (0..=255).for_each(|i| grid.palette.reset_index(i));
+                } else {
+                    for index in payload.split(|&b| b == b';').filter_map(Self::parse_u8) {
+                        grid.palette.reset_index(index);
+                    }
+                }
+                grid.dirty = true;
+            }
+            b"110" => {
+                grid.palette.reset_foreground();
+                grid.dirty = true;
+            }
+            b"111" => {
+                grid.palette.reset_background();
+                grid.dirty = true;
+            }
+            b"112" => {
+                grid.palette.reset_cursor();
+                grid.dirty = true;
+            }
+            _ => log::debug!("OSC não suportado: {}", String::from_utf8_lossy(command)),
+        }
+    }
+
+    /// OSC 4 - Define ou consulta pares `índice;cor` da paleta
+    fn osc_palette(&mut self, payload: &[u8], grid: &mut Grid, terminator: &[u8]) {
+        let mut parts = payload.split(|&b| b == b';');
+        while let (Some(index), Some(spec)) = (parts.next(), parts.next()) {
+            let Some(index) = Self::parse_u8(index) else {
+                continue;
+            };
+            if spec == b"?" {
+                let color = palette::format_color_spec(grid.palette[index]);
+                self.output.extend_from_slice(format!("\x1b]4;{};{}", index, color).as_bytes());
+                self.output.extend_from_slice(terminator);
+            } else if let Some(color) = palette::parse_color_spec(spec) {
+                grid.palette[index] = color;
+                grid.dirty = true;
+            }
+        }
+    }
+
+    /// OSC 10/11/12 - Cores dinâmicas a partir de `first`; parâmetros extras
+    /// seguem para a próxima (`OSC 10;fg;bg` define também o background)
+    fn osc_dynamic_colors(&mut self, first: u8, payload: &[u8], grid: &mut Grid, terminator: &[u8]) {
+        for (n, spec) in (first..=12).zip(payload.split(|&b| b == b';')) {
+            self.osc_dynamic_color(n, spec, grid, terminator);
+        }
+    }
+
+    /// Define ou consulta foreground (10), background (11) ou cursor (12)
+    fn osc_dynamic_color(&mut self, n: u8, spec: &[u8], grid: &mut Grid, terminator: &[u8]) {
+        let target = match n {
+            10 => &mut grid.palette.foreground,
+            11 => &mut grid.palette.background,
+            _ => &mut grid.palette.cursor,
+        };
+        if spec == b"?" {
+            let color = palette::format_color_spec(*target);
+            self.output.extend_from_slice(format!("\x1b]{};{}", n, color).as_bytes());
+            self.output.extend_from_slice(terminator);
+        } else if let Some(color) = palette::parse_color_spec(spec) {
+            *target = color;
+            grid.dirty = true;
+        }
+    }
+
+    /// OSC 7 - Diretório atual como `file://host/path`; hosts remotos são ignorados
+    fn osc_working_directory(&mut self, payload: &[u8]) {
+        let Some(rest) = payload.strip_prefix(b"file://") else {
+            return;
+        };
+        let split = rest.iter().position(|&b| b == b'/').unwrap_or(rest.len());
+        let (host, path) = (&rest[..split], &rest[split..]);
+
+        if path.is_empty() || !is_local_host(host) {
+            return;
+        }
+
+        let path = PathBuf::from(std::ffi::OsString::from_vec(percent_decode(path)));
+        self.cwd = Some(path.clone());
+        self.events.push(TermEvent::WorkingDirectory(path));
+    }
+
+    /// OSC 8 - Abre (`params;URI`) ou fecha (URI vazia) um hyperlink
+    fn osc_hyperlink(payload: &[u8], grid: &mut Grid) {
+        let Some(split) = payload.iter().position(|&b| b == b';') else {
+            return;
+        };
+        let (params, uri) = (&payload[..split], &payload[split + 1..]);
+
+        if uri.is_empty() {
+            grid.current_style.hyperlink = None;
+            return;
+        }
+
+        // Parâmetros `chave=valor` separados por ':'; só `id` é usado
+        let id = params
+            .split(|&b| b == b':')
+            .find_map(|param| param.strip_prefix(b"id="))
+            .filter(|id| !id.is_empty())
+            .map(|id| String::from_utf8_lossy(id).into_owned());
+        let link = Hyperlink {
+            id,
+            uri: String::from_utf8_lossy(uri).into_owned(),
+        };
+        grid.current_style.hyperlink = Some(grid.intern_hyperlink(link));
+    }
+
+    /// OSC 133 - Marcas de shell integration (FinalTerm): `A`, `B`, `C`, `D[;exit]`
+    /// Opções extras (`;aid=...`, `;k=...`) são ignoradas
+    fn osc_semantic_prompt(payload: &[u8], grid: &mut Grid) {
+        let mut params = payload.split(|&b| b == b';');
+        let mark = match params.next().unwrap_or_default() {
+            b"A" => SemanticMark::PromptStart,
+            b"B" => SemanticMark::InputStart,
+            b"C" => SemanticMark::OutputStart,
+            b"D" => {
+                let exit_code = params
+                    .next()
+                    .and_then(|code| std::str::from_utf8(code).ok()?.parse().ok());
+                SemanticMark::CommandEnd(exit_code)
+            }
+            _ => return,
+        };
+        grid.semantic_mark(mark);
+    }
+
+    /// OSC 9 - Notificação no formato do iTerm2 (`OSC 9 ; mensagem`)
+    /// Subcomandos numéricos do ConEmu (`9;4;...` etc.) são ignorados
+    fn osc_notify(&mut self, payload: &[u8]) {
+        if let Some(split) = payload.iter().position(|&b| b == b';')
+            && !payload[..split].is_empty()
+            && payload[..split].iter().all(u8::is_ascii_digit)
+        {
+            return;
+        }
+
+        let title = self.title.clone().unwrap_or_else(|| DEFAULT_TITLE.to_string());
+        let body = String::from_utf8_lossy(payload).into_owned();
+        self.events.push(TermEvent::Notification { title, body });
+    }
+
+    /// OSC 777 - Notificação do rxvt (`OSC 777 ; notify ; título ; corpo`)
+    fn osc_notify_rxvt(&mut self, payload: &[u8]) {
+        let mut parts = payload.splitn(3, |&b| b == b';');
+        if parts.next() != Some(b"notify") {
+            return;
+        }
+        let title = String::from_utf8_lossy(parts.next().unwrap_or_default()).into_owned();
+        let body = String::from_utf8_lossy(parts.next().unwrap_or_default()).into_owned();
+        self.events.push(TermEvent::Notification { title, body });
+    }
+
+    /// OSC 52 - Escrita (base64) ou leitura (`?`) do clipboard
+    fn osc_clipboard(&mut self, payload: &[u8], terminator: &'static [u8]) {
+        let Some(split) = payload.iter().position(|&b| b == b';') else {
+            return;
+        };
+        let (targets, data) = (&payload[..split], &payload[split + 1..]);
+
+        // Seleções reconhecidas; vazio equivale ao clipboard
+        let mut kinds: Vec<ClipboardKind> = targets
+            .iter()
+            .filter_map(|&b| match b {
+                b'c' => Some(ClipboardKind::Clipboard),
+                b'p' | b's' => Some(ClipboardKind::Primary),
+                _ => None, // Cut buffers 0–7 não existem aqui
+            })
+            .collect();
+        if targets.is_empty() {
+            kinds.push(ClipboardKind::Clipboard);
+        }
+        kinds.dedup();
+
+        if data == b"?" {
+            if let Some(&kind) = kinds.first() {
+                self.events.push(TermEvent::ClipboardLoad(kind, terminator));
+            }
+            return;
+        }
+
+        // Limite pelo tamanho decodificado antes de decodificar
+        if data.len() / 4 * 3 > CLIPBOARD_MAX_BYTES {
+            log::warn!("OSC 52 excede {} bytes, ignorado", CLIPBOARD_MAX_BYTES);
+            return;
+        }
+        let text = match BASE64.decode(data) {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(e) => {
+                log::debug!("OSC 52 com base64 inválido: {}", e);
+                return;
+            }
+        };
+        for kind in kinds {
+            self.events.push(TermEvent::ClipboardStore(kind, text.clone()));
+        }
+    }
+
+    /// Resposta a uma leitura de OSC 52; conteúdo acima do limite vai vazio
+    pub fn clipboard_reply(kind: ClipboardKind, text: &str, terminator: &[u8]) -> Vec<u8> {
+        let selection = match kind {
+            ClipboardKind::Clipboard => 'c',
+            ClipboardKind::Primary => 'p',
+        };
+        let data = if text.len() > CLIPBOARD_MAX_BYTES {
+            String::new()
+        } else {
+            BASE64.encode(text)
+        };
+        let mut reply = format!("\x1b]52;{};{}", selection, data).into_bytes();
+        reply.extend_from_slice(terminator);
+        reply
+    }
+
+    /// Índice decimal de paleta em um parâmetro de OSC
+    fn parse_u8(bytes: &[u8]) -> Option<u8> {
+        std::str::from_utf8(bytes).ok()?.parse().ok()
+    }
+
+    /// Salva o título atual na pilha (CSI 22 t)
+    fn push_title(&mut self) {
+        if self.title_stack.len() >= TITLE_STACK_MAX {
+            self.title_stack.remove(0);
+        }
+        self.title_stack.push(self.title.clone());
+    }
+
+    /// Restaura o último título salvo (CSI 23 t)
+    fn pop_title(&mut self) {
+        if let Some(title) = self.title_stack.pop() {
+            self.set_title(title);
         }
     }
 
+    /// Define o título e notifica a janela (`None` volta ao padrão)
+    fn set_title(&mut self, title: Option<String>) {
+        let text = title.clone().unwrap_or_else(|| DEFAULT_TITLE.to_string());
+        self.title = title;
+        self.events.push(TermEvent::Title(text));
+    }
+
     fn process_sgr(&mut self, grid: &mut Grid) {
         if self.params.is_empty() {
-            grid.current_style = CellStyle::default();
+            Self::reset_sgr(grid);
             return;
         }
 
         let mut i = 0;
         while i < self.params.len() {
+            // Sub-parâmetros separados por ':' pertencem ao parâmetro anterior
+            let mut end = i + 1;
+            while end < self.params.len() && self.subparams[end] {
+                end += 1;
+            }
+            if end - i > 1 {
+                self.process_sgr_group(i, end, grid);
+                i = end;
+                continue;
+            }
+
             match self.params[i] {
-                0 => grid.current_style = CellStyle::default(),
-                1 => grid.current_style.bold = true,
-                3 => grid.current_style.italic = true,
-                4 => grid.current_style.underline = true,
-                7 => grid.current_style.inverse = true,
-                22 => grid.current_style.bold = false,
-                23 => grid.current_style.italic = false,
-                24 => grid.current_style.underline = false,
-                27 => grid.current_style.inverse = false,
+                0 => Self::reset_sgr(grid),
+                1 => grid.current_style.attrs.insert(Attrs::BOLD),
+                2 => grid.current_style.attrs.insert(Attrs::DIM),
+                3 => grid.current_style.attrs.insert(Attrs::ITALIC),
+                4 => grid.current_style.underline = Underline::Single,
+                5 | 6 => grid.current_style.attrs.insert(Attrs::BLINK),
+                7 => grid.current_style.attrs.insert(Attrs::INVERSE),
+                8 => grid.current_style.attrs.insert(Attrs::HIDDEN),
+                9 => grid.current_style.attrs.insert(Attrs::STRIKETHROUGH),
+                21 => grid.current_style.underline = Underline::Double,
+                22 => grid.current_style.attrs.remove(Attrs::BOLD | Attrs::DIM),
+                23 => grid.current_style.attrs.remove(Attrs::ITALIC),
+                24 => grid.current_style.underline = Underline::None,
+                25 => grid.current_style.attrs.remove(Attrs::BLINK),
+                27 => grid.current_style.attrs.remove(Attrs::INVERSE),
+                28 => grid.current_style.attrs.remove(Attrs::HIDDEN),
+                29 => grid.current_style.attrs.remove(Attrs::STRIKETHROUGH),
                 30..=37 => {
-                    grid.current_style.fg = ANSI_COLORS[(self.params[i] - 30) as usize];
+                    grid.current_style.fg = Color::Indexed((self.params[i] - 30) as u8);
                 }
                 38 => {
                     // Extended foreground
-                    if i + 2 < self.params.len() && self.params[i + 1] == 5 {
-                        let color_idx = self.params[i + 2] as usize;
-                        if color_idx < 16 {
-                            grid.current_style.fg = ANSI_COLORS[color_idx];
-                        }
-                        i += 2;
-                    } else if i + 4 < self.params.len() && self.params[i + 1] == 2 {
-                        // True color
-                        let r = self.params[i + 2] as f32 / 255.0;
-                        let g = self.params[i + 3] as f32 / 255.0;
-                        let b = self.params[i + 4] as f32 / 255.0;
-                        grid.current_style.fg = [r, g, b, 1.0];
-                        i += 4;
+                    if let Some((color, used)) = self.extended_color(i + 1) {
+                        grid.current_style.fg = color;
+                        i += used;
                     }
                 }
-                39 => grid.current_style.fg = crate::config::FG_COLOR,
+                39 => grid.current_style.fg = Color::Foreground,
                 40..=47 => {
-                    grid.current_style.bg = ANSI_COLORS[(self.params[i] - 40) as usize];
+                    grid.current_style.bg = Color::Indexed((self.params[i] - 40) as u8);
                 }
                 48 => {
                     // Extended background
-                    if i + 2 < self.params.len() && self.params[i + 1] == 5 {
-                        let color_idx = self.params[i + 2] as usize;
-                        if color_idx < 16 {
-                            grid.current_style.bg = ANSI_COLORS[color_idx];
-                        }
-                        i += 2;
-                    } else if i + 4 < self.params.len() && self.params[i + 1] == 2 {
-                        let r = self.params[i + 2] as f32 / 255.0;
-                        let g = self.params[i + 3] as f32 / 255.0;
-                        let b = self.params[i + 4] as f32 / 255.0;
-                        grid.current_style.bg = [r, g, b, 1.0];
-                        i += 4;
+                    if let Some((color, used)) = self.extended_color(i + 1) {
+                        grid.current_style.bg = color;
+                        i += used;
+                    }
+                }
+                49 => grid.current_style.bg = Color::Background,
+                58 => {
+                    // Cor do sublinhado
+                    if let Some((color, used)) = self.extended_color(i + 1) {
+                        grid.current_style.underline_color = Some(color);
+                        i += used;
                     }
                 }
-                49 => grid.current_style.bg = crate::config::BG_COLOR,
+                53 => grid.current_style.attrs.insert(Attrs::OVERLINE),
+                55 => grid.current_style.attrs.remove(Attrs::OVERLINE),
+                59 => grid.current_style.underline_color = None,
                 90..=97 => {
-                    grid.current_style.fg = ANSI_COLORS[(self.params[i] - 90 + 8) as usize];
+                    grid.current_style.fg = Color::Indexed((self.params[i] - 90 + 8) as u8);
                 }
                 100..=107 => {
-                    grid.current_style.bg = ANSI_COLORS[(self.params[i] - 100 + 8) as usize];
+                    grid.current_style.bg = Color::Indexed((self.params[i] - 100 + 8) as u8);
                 }
                 _ => {}
             }
@@ -285,20 +1306,275 @@ impl AnsiParser {
         }
     }
 
+    /// SGR com sub-parâmetros: `4:x`, `38:5:n`, `38:2:cs:r:g:b` (e 48/58)
+    fn process_sgr_group(&self, start: usize, end: usize, grid: &mut Grid) {
+        let group = &self.params[start..end];
+        match group[0] {
+            4 => {
+                grid.current_style.underline = match group[1] {
+                    0 => Underline::None,
+                    1 => Underline::Single,
+                    2 => Underline::Double,
+                    3 => Underline::Curly,
+                    4 => Underline::Dotted,
+                    5 => Underline::Dashed,
+                    _ => return,
+                };
+            }
+            38 | 48 | 58 => {
+                let color = match group[1..] {
+                    [5, index, ..] => Color::Indexed(index.min(255) as u8),
+                    // Com ou sem o colorspace id (geralmente vazio)
+                    [2, _, r, g, b, ..] | [2, r, g, b] => {
+                        Color::Rgb(r.min(255) as u8, g.min(255) as u8, b.min(255) as u8)
+                    }
+                    _ => return,
+                };
+                match group[0] {
+                    38 => grid.current_style.fg = color,
+                    48 => grid.current_style.bg = color,
+                    _ => grid.current_style.underline_color = Some(color),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Cor estendida de SGR 38/48/58 a partir de `start`
+    /// `5;n` é um índice da paleta e `2;r;g;b` é true color
+    /// Retorna a cor e quantos parâmetros foram consumidos
+    fn extended_color(&self, start: usize) -> Option<(Color, usize)> {
+        match self.params.get(start)? {
+            5 => {
+                let index = *self.params.get(start + 1)?;
+                Some((Color::Indexed(index.min(255) as u8), 2))
+            }
+            2 => {
+                let channel = |k: usize| self.params.get(start + k).map(|&v| v.min(255) as u8);
+                Some((Color::Rgb(channel(1)?, channel(2)?, channel(3)?), 4))
+            }
+            _ => None,
+        }
+    }
+
+    /// SGR 0 - DECSCA e o hyperlink aberto não são afetados por SGR
+    fn reset_sgr(grid: &mut Grid) {
+        let protected = grid.current_style.attrs.contains(Attrs::PROTECTED);
+        grid.current_style = CellStyle {
+            hyperlink: grid.current_style.hyperlink,
+            ..CellStyle::default()
+        };
+        grid.current_style.attrs.set(Attrs::PROTECTED, protected);
+    }
+
+    /// Versão do pacote como número (major * 10000 + minor * 100 + patch)
+    fn version_number() -> u32 {
+        let part = |s: &str| s.parse::<u32>().unwrap_or(0);
+        part(env!("CARGO_PKG_VERSION_MAJOR")) * 10_000
+            + part(env!("CARGO_PKG_VERSION_MINOR")) * 100
+            + part(env!("CARGO_PKG_VERSION_PATCH"))
+    }
+
     fn get_param(&self, idx: usize, default: u16) -> u16 {
         self.params.get(idx).copied().filter(|&v| v > 0).unwrap_or(default)
     }
 
+    /// Fecha o parâmetro atual
+    fn push_param(&mut self) {
+        self.params.push(self.current_param);
+        self.subparams.push(self.current_is_sub);
+        self.current_param = 0;
+        self.current_is_sub = false;
+    }
+
     fn reset(&mut self) {
         self.state = State::Ground;
         self.params.clear();
+        self.subparams.clear();
         self.current_param = 0;
+        self.current_is_sub = false;
         self.intermediate.clear();
+        self.sixel = None;
     }
 }
 
+/// Host de uma URI `file://` refere-se a esta máquina
+fn is_local_host(host: &[u8]) -> bool {
+    if host.is_empty() || host == b"localhost" {
+        return true;
+    }
+    hostname().is_some_and(|name| name.eq_ignore_ascii_case(host))
+}
+
+/// Nome desta máquina via `gethostname` (Linux e macOS)
+fn hostname() -> Option<Vec<u8>> {
+    let mut buf = [0u8; 256];
+    // SAFETY: `buf` é válido para escrita de `buf.len()` bytes
+    if unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) } != 0 {
+        return None;
+    }
+    // Truncado sem NUL não é confiável
+    let len = buf.iter().position(|&b| b == 0)?;
+    Some(buf[..len].to_vec())
+}
+
+/// Hexadecimal (maiúsculo) usado por XTGETTCAP
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+/// Decodifica hexadecimal; `None` se inválido
+fn hex_decode(hex: &[u8]) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    hex.chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+/// Decodifica escapes `%XX` de uma URI
+fn percent_decode(bytes: &[u8]) -> Vec<u8> {
+    let hex = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let (Some(hi), Some(lo)) = (
+                bytes.get(i + 1).copied().and_then(hex),
+                bytes.get(i + 2).copied().and_then(hex),
+            )
+        {
+            out.push(hi << 4 | lo);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    out
+}
+
 impl Default for AnsiParser {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Caracteres da primeira linha após processar cada bloco em sequência
+    fn first_row(chunks: &[&[u8]]) -> String {
+        let mut grid = Grid::new(20, 2);
+        let mut parser = AnsiParser::new();
+        for chunk in chunks {
+            parser.process(chunk, &mut grid);
+        }
+        (0..grid.cols)
+            .map(|x| grid.get_cell(x, 0).c)
+            .collect::<String>()
+            .trim_end()
+            .to_string()
+    }
+
+    #[test]
+    fn utf8_split_across_process_calls() {
+        let bytes = "aé€𐍈b".as_bytes();
+        for split in 0..=bytes.len() {
+            let (head, tail) = bytes.split_at(split);
+            assert_eq!(first_row(&[head, tail]), "aé€𐍈b", "split em {split}");
+        }
+        let single: Vec<&[u8]> = bytes.chunks(1).collect();
+        assert_eq!(first_row(&single), "aé€𐍈b");
+    }
+
+    #[test]
+    fn utf8_overlong_forms() {
+        // C0/C1 nunca iniciam sequência; E0 80 e F0 80 são overlong já no segundo byte
+        assert_eq!(first_row(&[b"\xc0\xafx"]), "\u{fffd}\u{fffd}x");
+        assert_eq!(first_row(&[b"\xe0\x80\xafx"]), "\u{fffd}\u{fffd}\u{fffd}x");
+        assert_eq!(first_row(&[b"\xf0\x80\x80\xafx"]), "\u{fffd}\u{fffd}\u{fffd}\u{fffd}x");
+    }
+
+    #[test]
+    fn utf8_surrogates_and_out_of_range() {
+        // U+D800 codificado (ED A0 80) e U+110000 (F4 90 80 80)
+        assert_eq!(first_row(&[b"\xed\xa0\x80x"]), "\u{fffd}\u{fffd}\u{fffd}x");
+        assert_eq!(first_row(&[b"\xf4\x90\x80\x80x"]), "\u{fffd}\u{fffd}\u{fffd}\u{fffd}x");
+        // Último escalar válido antes dos surrogates ainda passa
+        assert_eq!(first_row(&[b"\xed\x9f\xbfx"]), "\u{d7ff}x");
+    }
+
+    #[test]
+    fn utf8_truncated_before_escape() {
+        // Sequência cortada por ESC vira um único U+FFFD e o CSI ainda é executado
+        assert_eq!(first_row(&[b"\xe2\x82\x1b[1mz"]), "\u{fffd}z");
+        // Mesmo com o ESC chegando em outra chamada
+        assert_eq!(first_row(&[b"\xf0\x9f", b"\x1b[31mx"]), "\u{fffd}x");
+        // Sequência cortada por outro líder reprocessa o líder
+        assert_eq!(first_row(&[b"\xe2\x82", b"\xc3\xa9"]), "\u{fffd}é");
+    }
+
+    #[test]
+    fn private_marker_is_not_sgr_or_cursor_motion() {
+        let mut grid = Grid::new(20, 4);
+        let mut parser = AnsiParser::new();
+        parser.process(b"\x1b[2;3H\x1b[>4;2m\x1b[?1m\x1b[>2A\x1b[?5H\x1b[>2J", &mut grid);
+        assert_eq!(grid.current_style, CellStyle::default());
+        assert_eq!((grid.cursor_x, grid.cursor_y), (2, 1));
+
+        parser.process(b"x", &mut grid);
+        assert_eq!(grid.get_cell(2, 1).c, 'x');
+
+        // DCH, ECH, CHA e REP com marcador privado também não são executados
+        parser.process(b"yz\x1b[2;3H\x1b[?5P\x1b[>3X\x1b[?9G\x1b[?2b", &mut grid);
+        let row: String = (2..6).map(|x| grid.get_cell(x, 1).c).collect();
+        assert_eq!(row, "xyz ");
+        assert_eq!((grid.cursor_x, grid.cursor_y), (2, 1));
+    }
+
+    #[test]
+    fn hyperlinks_are_deduplicated_and_reclaimed() {
+        let mut grid = Grid::new(20, 4);
+        let mut parser = AnsiParser::new();
+        for _ in 0..3 {
+            parser.process(b"\x1b]8;;http://x\x1b\\a\x1b]8;;\x1b\\", &mut grid);
+        }
+        assert_eq!(grid.hyperlinks.len(), 1);
+
+        parser.process(b"\x1b]8;;http://y\x1b\\b\x1b]8;;\x1b\\", &mut grid);
+        assert_eq!(grid.hyperlinks.len(), 2);
+
+        // RIS limpa a tela; sem scrollback, nenhum link continua referenciado
+        parser.process(b"\x1bc", &mut grid);
+        assert_eq!(grid.hyperlinks.len(), 0);
+    }
+
+    #[test]
+    fn osc7_accepts_this_host() {
+        let host = String::from_utf8(hostname().expect("gethostname")).unwrap();
+        let mut grid = Grid::new(20, 4);
+        let mut parser = AnsiParser::new();
+        parser.process(format!("\x1b]7;file://{host}/tmp/a%20b\x07").as_bytes(), &mut grid);
+        assert_eq!(parser.cwd(), Some(Path::new("/tmp/a b")));
+
+        parser.process(b"\x1b]7;file://not-this-host.invalid/etc\x07", &mut grid);
+        assert_eq!(parser.cwd(), Some(Path::new("/tmp/a b")));
+    }
+
+    #[test]
+    fn backspace_only_moves_the_cursor() {
+        let mut grid = Grid::new(4, 2);
+        let mut parser = AnsiParser::new();
+        parser.process(b"ab\x08", &mut grid);
+        assert_eq!(grid.get_cell(1, 0).c, 'b');
+        assert_eq!(grid.cursor_x, 1);
+
+        // Com quebra pendente, volta a partir da última coluna
+        parser.process(b"\rwxyz\x08", &mut grid);
+        assert_eq!(grid.get_cell(3, 0).c, 'z');
+        assert_eq!(grid.cursor_x, 2);
+    }
+}
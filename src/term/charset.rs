@@ -0,0 +1,102 @@
+//! Conjuntos de caracteres G0–G3
+//! Designação via `ESC ( ) * +`, locking shifts (SI/SO/LS2/LS3) e tradução
+
+/// Conjunto de caracteres designável
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Charset {
+    /// US-ASCII (`B`)
+    #[default]
+    Ascii,
+    /// DEC Special Graphics (`0`) - desenho de linhas
+    DecSpecialGraphics,
+    /// Reino Unido (`A`) - '#' vira '£'
+    Uk,
+}
+
+impl Charset {
+    /// Charset a partir do byte final da designação
+    pub fn from_designator(byte: u8) -> Option<Self> {
+        match byte {
+            b'B' => Some(Self::Ascii),
+            b'0' => Some(Self::DecSpecialGraphics),
+            b'A' => Some(Self::Uk),
+            _ => None,
+        }
+    }
+
+    /// Traduz um caractere por este charset
+    pub fn map(self, c: char) -> char {
+        match self {
+            Self::Ascii => c,
+            Self::Uk => match c {
+                '#' => '£',
+                _ => c,
+            },
+            Self::DecSpecialGraphics => match c {
+                '_' => ' ',
+                '`' => '◆',
+                'a' => '▒',
+                'b' => '␉',
+                'c' => '␌',
+                'd' => '␍',
+                'e' => '␊',
+                'f' => '°',
+                'g' => '±',
+                'h' => '␤',
+                'i' => '␋',
+                'j' => '┘',
+                'k' => '┐',
+                'l' => '┌',
+                'm' => '└',
+                'n' => '┼',
+                'o' => '⎺',
+                'p' => '⎻',
+                'q' => '─',
+                'r' => '⎼',
+                's' => '⎽',
+                't' => '├',
+                'u' => '┤',
+                'v' => '┴',
+                'w' => '┬',
+                'x' => '│',
+                'y' => '≤',
+                'z' => '≥',
+                '{' => 'π',
+                '|' => '≠',
+                '}' => '£',
+                '~' => '·',
+                _ => c,
+            },
+        }
+    }
+}
+
+/// Designações G0–G3 e o conjunto ativo em GL
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Charsets {
+    slots: [Charset; 4],
+    /// Índice (0–3) do conjunto invocado em GL
+    gl: usize,
+}
+
+impl Charsets {
+    /// Designa `charset` para G`slot`
+    pub fn designate(&mut self, slot: usize, charset: Charset) {
+        if let Some(target) = self.slots.get_mut(slot) {
+            *target = charset;
+        }
+    }
+
+    /// Locking shift: invoca G`slot` em GL
+    pub fn lock_shift(&mut self, slot: usize) {
+        if slot < self.slots.len() {
+            self.gl = slot;
+        }
+    }
+
+    /// Traduz um caractere pelo GL, ou por G`single_shift` (SS2/SS3)
+    pub fn map(&self, c: char, single_shift: Option<usize>) -> char {
+        let slot = single_shift.unwrap_or(self.gl);
+        self.slots.get(slot).copied().unwrap_or_default().map(c)
+    }
+}
@@ -0,0 +1,28 @@
+//! Eventos do terminal para a janela
+//! Gerados pelo parser e consumidos pelo loop principal
+
+use std::path::PathBuf;
+
+/// Seleção alvo de um OSC 52
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClipboardKind {
+    /// `c` - clipboard
+    Clipboard,
+    /// `p` / `s` - seleção primária
+    Primary,
+}
+
+/// Efeito de uma sequência fora do grid
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TermEvent {
+    /// Novo título da janela (OSC 0/2, pop da pilha de títulos)
+    Title(String),
+    /// Escrita no clipboard (OSC 52), já decodificada
+    ClipboardStore(ClipboardKind, String),
+    /// Leitura do clipboard (OSC 52 com `?`); a resposta usa `terminator`
+    ClipboardLoad(ClipboardKind, &'static [u8]),
+    /// Diretório atual informado pelo shell (OSC 7)
+    WorkingDirectory(PathBuf),
+    /// Notificação de desktop (OSC 9 / OSC 777)
+    Notification { title: String, body: String },
+}
@@ -1,37 +1,139 @@
-/// Grid de células do terminal
-/// Buffer duplo para renderização eficiente
+//! Grid de células do terminal
+//! Buffer duplo para renderização eficiente
 
-use crate::config::{SCROLLBACK_LINES, FG_COLOR, BG_COLOR};
+use std::collections::HashSet;
+use std::sync::Arc;
+use crate::config::{CELL_HEIGHT, CELL_WIDTH, SCROLLBACK_LINES, TAB_WIDTH};
+use unicode_width::UnicodeWidthChar;
+use super::charset::Charsets;
+use super::hyperlink::{Hyperlink, Hyperlinks, LinkId};
+use super::image::{Image, ImageFragment, Placement};
+use super::modes::{Mode, TermModes};
+use super::palette::{Color, Palette};
+use super::row::{Row, SemanticMark, Zone};
+use std::ops::RangeInclusive;
+
+/// Zero Width Joiner - une o próximo caractere ao cluster anterior
+const ZWJ: char = '\u{200d}';
+
+/// Estilo de sublinhado (SGR 4:x)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Underline {
+    #[default]
+    None,
+    Single,
+    Double,
+    Curly,
+    Dotted,
+    Dashed,
+}
+
+/// Atributos booleanos de SGR compactados em bits
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Attrs(u16);
+
+impl Attrs {
+    pub const BOLD: Self = Self(1 << 0);
+    pub const DIM: Self = Self(1 << 1);
+    pub const ITALIC: Self = Self(1 << 2);
+    pub const BLINK: Self = Self(1 << 3);
+    pub const INVERSE: Self = Self(1 << 4);
+    pub const HIDDEN: Self = Self(1 << 5);
+    pub const STRIKETHROUGH: Self = Self(1 << 6);
+    pub const OVERLINE: Self = Self(1 << 7);
+    /// Protegido contra apagamento seletivo (DECSCA)
+    pub const PROTECTED: Self = Self(1 << 8);
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+
+    pub fn remove(&mut self, other: Self) {
+        self.0 &= !other.0;
+    }
+
+    pub fn set(&mut self, other: Self, enable: bool) {
+        if enable {
+            self.insert(other);
+        } else {
+            self.remove(other);
+        }
+    }
+}
+
+impl std::ops::BitOr for Attrs {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
 
 /// Estilo de uma célula
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct CellStyle {
-    pub fg: [f32; 4],
-    pub bg: [f32; 4],
-    pub bold: bool,
-    pub italic: bool,
-    pub underline: bool,
-    pub inverse: bool,
+    pub fg: Color,
+    pub bg: Color,
+    pub attrs: Attrs,
+    pub underline: Underline,
+    /// Cor do sublinhado (SGR 58); `None` usa o foreground
+    pub underline_color: Option<Color>,
+    /// Hyperlink OSC 8 na tabela `Grid::hyperlinks`
+    pub hyperlink: Option<LinkId>,
 }
 
 impl Default for CellStyle {
     fn default() -> Self {
         Self {
-            fg: FG_COLOR,
-            bg: BG_COLOR,
-            bold: false,
-            italic: false,
-            underline: false,
-            inverse: false,
+            fg: Color::Foreground,
+            bg: Color::Background,
+            attrs: Attrs::default(),
+            underline: Underline::None,
+            underline_color: None,
+            hyperlink: None,
         }
     }
 }
 
+/// Alcance de ED / EL
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EraseMode {
+    /// Do cursor até o fim (inclusive)
+    ToEnd,
+    /// Do início até o cursor (inclusive)
+    ToStart,
+    /// Tela ou linha inteira
+    All,
+    /// Só o scrollback (ED 3)
+    Scrollback,
+}
+
+/// Largura de uma célula
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CellWidth {
+    /// Caractere de uma coluna
+    #[default]
+    Normal,
+    /// Caractere de duas colunas (CJK, emoji); a célula seguinte é um spacer
+    Wide,
+    /// Segunda metade de um caractere largo, não tem conteúdo próprio
+    Spacer,
+}
+
 /// Uma célula no grid
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct Cell {
     pub c: char,
+    /// Marcas combinantes e sequências ZWJ anexadas a `c` (grapheme cluster)
+    pub zerowidth: Option<Box<[char]>>,
+    pub width: CellWidth,
     pub style: CellStyle,
+    /// Reservado para renderização incremental por célula
+    #[allow(dead_code)]
     pub dirty: bool,
 }
 
@@ -39,71 +141,374 @@ impl Default for Cell {
     fn default() -> Self {
         Self {
             c: ' ',
+            zerowidth: None,
+            width: CellWidth::Normal,
             style: CellStyle::default(),
             dirty: true,
         }
     }
 }
 
+/// Cursor salvo (DECSC / modo 1049)
+#[derive(Clone, Copy, Debug, Default)]
+struct SavedCursor {
+    x: usize,
+    y: usize,
+    style: CellStyle,
+    /// DECOM no momento do save
+    origin: bool,
+    /// Cursor estava além da última coluna, aguardando quebra de linha
+    pending_wrap: bool,
+    /// Designações G0–G3 e GL
+    charsets: Charsets,
+}
+
 /// Grid do terminal com scrollback
 pub struct Grid {
     /// Células visíveis
-    cells: Vec<Vec<Cell>>,
+    cells: Vec<Row>,
+    /// Buffer inativo (primário enquanto a tela alternativa está ativa e vice-versa)
+    inactive_cells: Vec<Row>,
+    /// Tela alternativa ativa (sem scrollback)
+    alt_screen: bool,
+    /// Cursor salvo da tela ativa (DECSC)
+    saved_cursor: Option<SavedCursor>,
+    /// Cursor salvo da tela inativa
+    inactive_saved_cursor: Option<SavedCursor>,
     /// Scrollback buffer
-    scrollback: Vec<Vec<Cell>>,
+    scrollback: Vec<Row>,
+    /// Linhas de scrollback acima da tela exibida (0 = tela ao vivo)
+    display_offset: usize,
+    /// Linhas selecionadas, em índices de `line` (scrollback seguido da tela)
+    selection: Option<RangeInclusive<usize>>,
+    /// Zona semântica atual (OSC 133), herdada pelas linhas novas
+    zone: Zone,
     /// Dimensões
     pub cols: usize,
     pub rows: usize,
     /// Posição do cursor
     pub cursor_x: usize,
     pub cursor_y: usize,
+    /// Região de scroll (DECSTBM), linhas inclusivas
+    scroll_top: usize,
+    scroll_bottom: usize,
+    /// Tab stops por coluna (HTS / TBC)
+    tabs: Vec<bool>,
     /// Estilo atual
     pub current_style: CellStyle,
+    /// Modos ANSI e DEC privados
+    pub modes: TermModes,
+    /// Paleta de 256 cores
+    pub palette: Palette,
+    /// Charsets G0–G3 (parte do estado salvo por DECSC)
+    pub charsets: Charsets,
+    /// Links referenciados pelas células, inclusive no scrollback
+    pub hyperlinks: Hyperlinks,
     /// Flag de dirty global
     pub dirty: bool,
 }
 
 impl Grid {
     pub fn new(cols: usize, rows: usize) -> Self {
-        let cells = vec![vec![Cell::default(); cols]; rows];
-        
+        let cells = vec![Row::new(cols, Cell::default()); rows];
+
         Self {
+            inactive_cells: cells.clone(),
             cells,
+            alt_screen: false,
+            saved_cursor: None,
+            inactive_saved_cursor: None,
             scrollback: Vec::with_capacity(SCROLLBACK_LINES),
+            display_offset: 0,
+            selection: None,
+            zone: Zone::None,
             cols,
             rows,
             cursor_x: 0,
             cursor_y: 0,
+            scroll_top: 0,
+            scroll_bottom: rows.saturating_sub(1),
+            tabs: Self::default_tabs(cols),
             current_style: CellStyle::default(),
+            modes: TermModes::default(),
+            palette: Palette::new(),
+            charsets: Charsets::default(),
+            hyperlinks: Hyperlinks::default(),
             dirty: true,
         }
     }
 
     /// Escreve um caractere na posição do cursor
     pub fn write_char(&mut self, c: char) {
+        let width = match c.width() {
+            Some(w) => w,
+            None => return, // Controles não ocupam células
+        };
+
+        // Zero-width ou continuação de ZWJ: anexa ao cluster anterior
+        if width == 0 || self.previous_ends_with_zwj() {
+            self.push_zerowidth(c);
+            return;
+        }
+
+        if width > self.cols {
+            return;
+        }
+
         if self.cursor_x >= self.cols {
-            self.newline();
+            if self.modes.autowrap {
+                self.newline();
+            } else {
+                self.cursor_x = self.cols - 1;
+            }
+        }
+
+        // Caractere largo não cabe na última coluna
+        if width == 2 && self.cursor_x + 1 >= self.cols {
+            if self.modes.autowrap {
+                self.erase_wide_at(self.cursor_x, self.cursor_y);
+                self.cells[self.cursor_y][self.cursor_x] = Cell::default();
+                self.newline();
+            } else {
+                self.cursor_x = self.cols - 2;
+            }
         }
-        
+
         if self.cursor_y < self.rows && self.cursor_x < self.cols {
-            self.cells[self.cursor_y][self.cursor_x] = Cell {
+            let (x, y) = (self.cursor_x, self.cursor_y);
+            if self.modes.insert {
+                self.insert_blank(width);
+            }
+            self.erase_wide_at(x, y);
+
+            let cell_width = if width == 2 { CellWidth::Wide } else { CellWidth::Normal };
+            self.cells[y][x] = Cell {
                 c,
+                zerowidth: None,
+                width: cell_width,
                 style: self.current_style,
                 dirty: true,
             };
-            self.cursor_x += 1;
+
+            if width == 2 {
+                self.erase_wide_at(x + 1, y);
+                self.cells[y][x + 1] = Cell {
+                    width: CellWidth::Spacer,
+                    style: self.current_style,
+                    ..Cell::default()
+                };
+            }
+
+            self.cursor_x += width;
+            if !self.modes.autowrap {
+                self.cursor_x = self.cursor_x.min(self.cols - 1);
+            }
             self.dirty = true;
         }
     }
 
+    /// Célula apagada: usa o background do estilo atual (BCE)
+    fn blank_cell(&self) -> Cell {
+        Cell {
+            style: CellStyle {
+                bg: self.current_style.bg,
+                ..CellStyle::default()
+            },
+            ..Cell::default()
+        }
+    }
+
+    /// Linha apagada com o background atual
+    fn blank_line(&self) -> Row {
+        Row::new(self.cols, self.blank_cell())
+    }
+
+    /// Insere `n` células em branco no cursor, empurrando o resto da linha (ICH)
+    pub fn insert_blank(&mut self, n: usize) {
+        let (x, y) = (self.cursor_x.min(self.cols - 1), self.cursor_y);
+        let n = n.min(self.cols - x);
+        let blank = self.blank_cell();
+        self.erase_wide_at(x, y);
+
+        let row = &mut self.cells[y];
+        row[x..].rotate_right(n);
+        for cell in &mut row[x..x + n] {
+            *cell = blank.clone();
+        }
+        // Caractere largo empurrado para fora perde o spacer
+        if let Some(last) = row.last_mut()
+            && last.width == CellWidth::Wide
+        {
+            *last = blank;
+        }
+        self.dirty = true;
+    }
+
+    /// Remove `n` células no cursor, puxando o resto da linha (DCH)
+    pub fn delete_chars(&mut self, n: usize) {
+        let (x, y) = (self.cursor_x.min(self.cols - 1), self.cursor_y);
+        let n = n.min(self.cols - x);
+        let blank = self.blank_cell();
+        self.erase_wide_at(x, y);
+        // Spacer cujo caractere largo será removido
+        if x + n < self.cols && self.cells[y][x + n].width == CellWidth::Spacer {
+            self.cells[y][x + n] = blank.clone();
+        }
+
+        let row = &mut self.cells[y];
+        row[x..].rotate_left(n);
+        let cols = row.len();
+        for cell in &mut row[cols - n..] {
+            *cell = blank.clone();
+        }
+        self.dirty = true;
+    }
+
+    /// Apaga `n` células a partir do cursor sem mover o resto (ECH)
+    pub fn erase_chars(&mut self, n: usize) {
+        let (x, y) = (self.cursor_x.min(self.cols - 1), self.cursor_y);
+        let end = (x + n.max(1)).min(self.cols);
+        let blank = self.blank_cell();
+        self.erase_wide_at(x, y);
+        self.erase_wide_at(end - 1, y);
+
+        for cell in &mut self.cells[y][x..end] {
+            *cell = blank.clone();
+        }
+        self.dirty = true;
+    }
+
+    /// Insere `n` linhas em branco no cursor, dentro da região de scroll (IL)
+    pub fn insert_lines(&mut self, n: usize) {
+        let (top, bottom) = (self.scroll_top, self.scroll_bottom);
+        if !(top..=bottom).contains(&self.cursor_y) {
+            return;
+        }
+
+        for _ in 0..n.min(bottom + 1 - self.cursor_y) {
+            self.cells.remove(bottom);
+            self.cells.insert(self.cursor_y, self.blank_line());
+        }
+        self.cursor_x = 0;
+        self.dirty = true;
+    }
+
+    /// Remove `n` linhas no cursor, dentro da região de scroll (DL)
+    pub fn delete_lines(&mut self, n: usize) {
+        let (top, bottom) = (self.scroll_top, self.scroll_bottom);
+        if !(top..=bottom).contains(&self.cursor_y) {
+            return;
+        }
+
+        for _ in 0..n.min(bottom + 1 - self.cursor_y) {
+            self.cells.remove(self.cursor_y);
+            self.cells.insert(bottom, self.blank_line());
+        }
+        self.cursor_x = 0;
+        self.dirty = true;
+    }
+
+    /// Posição da célula escrita por último (base do grapheme cluster)
+    fn previous_cell(&self) -> Option<(usize, usize)> {
+        if self.cursor_x == 0 || self.cursor_y >= self.rows {
+            return None;
+        }
+        let mut x = self.cursor_x.min(self.cols) - 1;
+        if self.cells[self.cursor_y][x].width == CellWidth::Spacer && x > 0 {
+            x -= 1;
+        }
+        Some((x, self.cursor_y))
+    }
+
+    fn previous_ends_with_zwj(&self) -> bool {
+        self.previous_cell()
+            .and_then(|(x, y)| self.cells[y][x].zerowidth.as_ref())
+            .is_some_and(|chars| chars.last() == Some(&ZWJ))
+    }
+
+    /// Anexa um caractere zero-width à célula anterior
+    fn push_zerowidth(&mut self, c: char) {
+        let Some((x, y)) = self.previous_cell() else {
+            return;
+        };
+        let cell = &mut self.cells[y][x];
+        let mut chars = cell.zerowidth.take().map(Vec::from).unwrap_or_default();
+        chars.push(c);
+        cell.zerowidth = Some(chars.into_boxed_slice());
+        cell.dirty = true;
+        self.dirty = true;
+    }
+
+    /// Desfaz um caractere largo que será parcialmente sobrescrito em (x, y)
+    fn erase_wide_at(&mut self, x: usize, y: usize) {
+        if x >= self.cols || y >= self.rows {
+            return;
+        }
+        match self.cells[y][x].width {
+            CellWidth::Wide if x + 1 < self.cols => {
+                self.cells[y][x + 1] = Cell::default();
+            }
+            CellWidth::Spacer if x > 0 => {
+                self.cells[y][x - 1] = Cell::default();
+            }
+            _ => {}
+        }
+    }
+
+    /// Line feed (LF/VT/FF) - também faz CR em modo LNM
+    pub fn linefeed(&mut self) {
+        if self.modes.linefeed_newline {
+            self.newline();
+        } else {
+            self.index();
+        }
+    }
+
     /// Nova linha
     pub fn newline(&mut self) {
         self.cursor_x = 0;
-        if self.cursor_y + 1 >= self.rows {
-            self.scroll_up();
-        } else {
+        self.index();
+    }
+
+    /// Index (IND) - desce uma linha, rolando na margem inferior
+    pub fn index(&mut self) {
+        if self.cursor_y == self.scroll_bottom {
+            self.scroll_up(1);
+        } else if self.cursor_y + 1 < self.rows {
             self.cursor_y += 1;
+        } else {
+            return;
+        }
+        // Linha nova herda a zona semântica atual
+        self.cells[self.cursor_y].zone = self.zone;
+    }
+
+    /// Reverse index (RI) - sobe uma linha, rolando na margem superior
+    pub fn reverse_index(&mut self) {
+        if self.cursor_y == self.scroll_top {
+            self.scroll_down(1);
+        } else if self.cursor_y > 0 {
+            self.cursor_y -= 1;
+        }
+    }
+
+    /// Define a região de scroll (DECSTBM), linhas 0-based inclusivas
+    /// Região inválida volta para a tela inteira
+    pub fn set_scroll_region(&mut self, top: usize, bottom: usize) {
+        let bottom = bottom.min(self.rows.saturating_sub(1));
+        if top < bottom {
+            self.scroll_top = top;
+            self.scroll_bottom = bottom;
+        } else {
+            self.scroll_top = 0;
+            self.scroll_bottom = self.rows.saturating_sub(1);
         }
+        self.goto(0, 0);
+    }
+
+    /// Região de scroll atual (DECSTBM), linhas 0-based inclusivas
+    pub fn scroll_region(&self) -> (usize, usize) {
+        (self.scroll_top, self.scroll_bottom)
     }
 
     /// Carriage return
@@ -111,74 +516,186 @@ impl Grid {
         self.cursor_x = 0;
     }
 
-    /// Scroll up uma linha
-    fn scroll_up(&mut self) {
-        // Move primeira linha para scrollback
-        if self.scrollback.len() >= SCROLLBACK_LINES {
-            self.scrollback.remove(0);
+    /// Scroll up de `n` linhas dentro da região de scroll
+    pub fn scroll_up(&mut self, n: usize) {
+        let (top, bottom) = (self.scroll_top, self.scroll_bottom);
+        // Só a tela primária inteira alimenta o scrollback; regiões parciais descartam
+        let full_screen = top == 0 && bottom + 1 == self.rows && !self.alt_screen;
+
+        for _ in 0..n.min(bottom + 1 - top) {
+            let line = self.cells.remove(top);
+            if full_screen {
+                if self.scrollback.len() >= SCROLLBACK_LINES {
+                    self.scrollback.remove(0);
+                    // Índices de linha deslocam com a remoção
+                    self.selection = self
+                        .selection
+                        .take()
+                        .filter(|range| *range.start() > 0)
+                        .map(|range| range.start() - 1..=range.end() - 1);
+                } else if self.display_offset > 0 {
+                    // Mantém estável a parte do histórico em exibição
+                    self.display_offset += 1;
+                }
+                self.scrollback.push(line);
+            }
+
+            // Nova linha vazia na margem inferior
+            self.cells.insert(bottom, self.blank_line());
+        }
+        self.dirty = true;
+    }
+
+    /// Scroll down de `n` linhas dentro da região de scroll
+    pub fn scroll_down(&mut self, n: usize) {
+        let (top, bottom) = (self.scroll_top, self.scroll_bottom);
+
+        for _ in 0..n.min(bottom + 1 - top) {
+            self.cells.remove(bottom);
+            self.cells.insert(top, self.blank_line());
         }
-        let first_line = self.cells.remove(0);
-        self.scrollback.push(first_line);
-        
-        // Adiciona nova linha vazia no final
-        self.cells.push(vec![Cell::default(); self.cols]);
         self.dirty = true;
     }
 
-    /// Backspace
+    /// Backspace: só move o cursor; apagar é papel de EL/DCH
+    /// Com quebra pendente, parte da última coluna
     pub fn backspace(&mut self) {
-        if self.cursor_x > 0 {
-            self.cursor_x -= 1;
-            self.cells[self.cursor_y][self.cursor_x] = Cell::default();
-            self.dirty = true;
+        let x = self.cursor_x.min(self.cols - 1);
+        self.cursor_x = x.saturating_sub(1);
+        self.dirty = true;
+    }
+
+    /// Tab stops padrão a cada `TAB_WIDTH` colunas
+    fn default_tabs(cols: usize) -> Vec<bool> {
+        (0..cols).map(|x| x > 0 && x % TAB_WIDTH == 0).collect()
+    }
+
+    /// Avança até a n-ésima próxima tab stop (HT / CHT)
+    pub fn tab(&mut self, count: usize) {
+        let mut x = self.cursor_x.min(self.cols - 1);
+        for _ in 0..count {
+            match (x + 1..self.cols).find(|&col| self.tabs[col]) {
+                Some(col) => x = col,
+                None => {
+                    x = self.cols - 1;
+                    break;
+                }
+            }
         }
+        self.cursor_x = x;
     }
 
-    /// Tab
-    pub fn tab(&mut self) {
-        let next_tab = (self.cursor_x / 8 + 1) * 8;
-        self.cursor_x = next_tab.min(self.cols - 1);
+    /// Volta até a n-ésima tab stop anterior (CBT)
+    pub fn back_tab(&mut self, count: usize) {
+        let mut x = self.cursor_x.min(self.cols - 1);
+        for _ in 0..count {
+            match (0..x).rev().find(|&col| self.tabs[col]) {
+                Some(col) => x = col,
+                None => {
+                    x = 0;
+                    break;
+                }
+            }
+        }
+        self.cursor_x = x;
+    }
+
+    /// Define uma tab stop na coluna do cursor (HTS)
+    pub fn set_tab_stop(&mut self) {
+        let x = self.cursor_x.min(self.cols - 1);
+        self.tabs[x] = true;
+    }
+
+    /// Remove a tab stop da coluna do cursor, ou todas (TBC)
+    pub fn clear_tab_stops(&mut self, all: bool) {
+        if all {
+            self.tabs.fill(false);
+        } else {
+            let x = self.cursor_x.min(self.cols - 1);
+            self.tabs[x] = false;
+        }
     }
 
     /// Limpa a tela
     pub fn clear(&mut self) {
         for row in &mut self.cells {
-            for cell in row {
-                *cell = Cell::default();
-            }
+            *row = Row::new(self.cols, Cell::default());
         }
         self.cursor_x = 0;
         self.cursor_y = 0;
         self.dirty = true;
     }
 
-    /// Limpa do cursor até o fim da linha
-    pub fn clear_to_end_of_line(&mut self) {
-        for x in self.cursor_x..self.cols {
-            self.cells[self.cursor_y][x] = Cell::default();
+    /// Erase in display (ED / DECSED)
+    /// `selective` preserva células protegidas por DECSCA
+    pub fn erase_in_display(&mut self, mode: EraseMode, selective: bool) {
+        let (x, y) = (self.cursor_x.min(self.cols - 1), self.cursor_y);
+        match mode {
+            EraseMode::ToEnd => {
+                self.erase_range(y, x, self.cols, selective);
+                for row in (y + 1)..self.rows {
+                    self.erase_range(row, 0, self.cols, selective);
+                }
+            }
+            EraseMode::ToStart => {
+                for row in 0..y {
+                    self.erase_range(row, 0, self.cols, selective);
+                }
+                self.erase_range(y, 0, x + 1, selective);
+            }
+            EraseMode::All => {
+                for row in 0..self.rows {
+                    self.erase_range(row, 0, self.cols, selective);
+                    self.cells[row].clear_marks();
+                }
+            }
+            EraseMode::Scrollback => {
+                self.scrollback.clear();
+                self.display_offset = 0;
+                self.selection = None;
+                self.prune_hyperlinks();
+                self.dirty = true;
+            }
         }
-        self.dirty = true;
     }
 
-    /// Limpa do cursor até o fim da tela
-    pub fn clear_to_end_of_screen(&mut self) {
-        self.clear_to_end_of_line();
-        for y in (self.cursor_y + 1)..self.rows {
-            for x in 0..self.cols {
-                self.cells[y][x] = Cell::default();
-            }
+    /// Erase in line (EL / DECSEL)
+    pub fn erase_in_line(&mut self, mode: EraseMode, selective: bool) {
+        let (x, y) = (self.cursor_x.min(self.cols - 1), self.cursor_y);
+        match mode {
+            EraseMode::ToEnd => self.erase_range(y, x, self.cols, selective),
+            EraseMode::ToStart => self.erase_range(y, 0, x + 1, selective),
+            EraseMode::All => self.erase_range(y, 0, self.cols, selective),
+            EraseMode::Scrollback => {}
         }
-        self.dirty = true;
     }
 
-    /// Limpa uma linha específica
-    pub fn clear_line(&mut self, y: usize) {
-        if y < self.rows {
-            for x in 0..self.cols {
-                self.cells[y][x] = Cell::default();
+    /// Apaga as colunas `start..end` da linha `y` com o background atual
+    fn erase_range(&mut self, y: usize, start: usize, end: usize, selective: bool) {
+        if y >= self.rows || start >= end {
+            return;
+        }
+        let blank = self.blank_cell();
+        let erasable = |cell: &Cell| !selective || !cell.style.attrs.contains(Attrs::PROTECTED);
+
+        // Caracteres largos cortados nas bordas
+        if erasable(&self.cells[y][start]) {
+            self.erase_wide_at(start, y);
+        }
+        if erasable(&self.cells[y][end - 1]) {
+            self.erase_wide_at(end - 1, y);
+        }
+
+        for cell in &mut self.cells[y][start..end] {
+            if erasable(cell) {
+                *cell = blank.clone();
             }
-            self.dirty = true;
         }
+        // Linha inteira apagada leva junto as imagens
+        if start == 0 && end == self.cols && !selective {
+            self.cells[y].images.clear();
+        }
+        self.dirty = true;
     }
 
     /// Move o cursor
@@ -187,10 +704,41 @@ impl Grid {
         self.cursor_y = y.min(self.rows.saturating_sub(1));
     }
 
+    /// Posiciona o cursor (CUP), relativo à região de scroll em origin mode
+    pub fn goto(&mut self, x: usize, y: usize) {
+        if self.modes.origin {
+            let y = (self.scroll_top + y).min(self.scroll_bottom);
+            self.move_cursor(x, y);
+        } else {
+            self.move_cursor(x, y);
+        }
+    }
+
+    /// Posiciona só a linha (VPA), respeitando origin mode
+    pub fn goto_line(&mut self, y: usize) {
+        let x = self.cursor_x;
+        self.goto(x, y);
+    }
+
+    /// Posição do cursor para CPR (0-based), relativa à região em origin mode
+    pub fn cursor_report_position(&self) -> (usize, usize) {
+        let x = self.cursor_x.min(self.cols.saturating_sub(1));
+        let y = if self.modes.origin {
+            self.cursor_y.saturating_sub(self.scroll_top)
+        } else {
+            self.cursor_y
+        };
+        (x, y)
+    }
+
     /// Move cursor relativo
+    /// Movimento vertical para nas margens quando o cursor está dentro da região
     pub fn move_cursor_relative(&mut self, dx: isize, dy: isize) {
         let new_x = (self.cursor_x as isize + dx).max(0) as usize;
-        let new_y = (self.cursor_y as isize + dy).max(0) as usize;
+        let mut new_y = (self.cursor_y as isize + dy).max(0) as usize;
+        if (self.scroll_top..=self.scroll_bottom).contains(&self.cursor_y) {
+            new_y = new_y.clamp(self.scroll_top, self.scroll_bottom);
+        }
         self.move_cursor(new_x, new_y);
     }
 
@@ -201,30 +749,455 @@ impl Grid {
 
     /// Redimensiona o grid
     pub fn resize(&mut self, cols: usize, rows: usize) {
-        let mut new_cells = vec![vec![Cell::default(); cols]; rows];
-        
-        // Copia células existentes
-        for y in 0..rows.min(self.rows) {
-            for x in 0..cols.min(self.cols) {
-                new_cells[y][x] = self.cells[y][x];
-            }
+        self.cells = Self::resize_cells(&self.cells, cols, rows);
+        self.inactive_cells = Self::resize_cells(&self.inactive_cells, cols, rows);
+        // O histórico acompanha a largura, para ser exibido com `display_offset`
+        for row in &mut self.scrollback {
+            Self::resize_row(row, cols);
         }
-        
-        self.cells = new_cells;
         self.cols = cols;
         self.rows = rows;
         self.cursor_x = self.cursor_x.min(cols.saturating_sub(1));
         self.cursor_y = self.cursor_y.min(rows.saturating_sub(1));
+        self.scroll_top = 0;
+        self.scroll_bottom = rows.saturating_sub(1);
+        self.tabs = Self::default_tabs(cols);
+        self.display_offset = 0;
+        self.selection = None;
+        self.dirty = true;
+    }
+
+    /// Ancora uma imagem no cursor, uma faixa por linha
+    /// Com `scroll`, desce o cursor (rolando se preciso) até a última linha da
+    /// imagem; sem, o cursor fica parado e as linhas além da tela se perdem
+    pub fn place_image(&mut self, image: &Arc<Image>, placement: &Placement, scroll: bool) {
+        let (cell_width, cell_height) = (CELL_WIDTH as u32, CELL_HEIGHT as u32);
+        let col = self.cursor_x.min(self.cols - 1);
+        let top = self.cursor_y;
+
+        for (i, fragment) in placement.fragments(image, col, cell_height).into_iter().enumerate() {
+            // Faixa vazia: nada mais a desenhar (nem a rolar)
+            if fragment.height == 0 || fragment.source[3] <= 0.0 {
+                break;
+            }
+            let y = if scroll {
+                if i > 0 {
+                    self.index();
+                }
+                self.cursor_y
+            } else {
+                top + i
+            };
+            let Some(row) = self.cells.get_mut(y) else {
+                break;
+            };
+            // Faixas totalmente encobertas não aparecem mais
+            row.images.retain(|old| !fragment.covers(old, cell_width));
+            row.images.push(fragment);
+        }
+        self.dirty = true;
+    }
+
+    /// Faixas de imagem com a linha: `Some(y)` na tela e `None` no scrollback
+    pub fn images(&self) -> impl Iterator<Item = (Option<usize>, &ImageFragment)> {
+        let screen = self.cells.iter().enumerate().map(|(y, row)| (Some(y), row));
+        let scrollback = self.scrollback.iter().map(|row| (None, row));
+        screen
+            .chain(scrollback)
+            .flat_map(|(line, row)| row.images.iter().map(move |fragment| (line, fragment)))
+    }
+
+    /// Remove as faixas de imagem para as quais `delete(linha, faixa)` vale
+    /// A linha é `Some(y)` na tela e `None` no scrollback, incluído com `scrollback`
+    pub fn delete_images(
+        &mut self,
+        scrollback: bool,
+        mut delete: impl FnMut(Option<usize>, &ImageFragment) -> bool,
+    ) {
+        for (y, row) in self.cells.iter_mut().enumerate() {
+            row.images.retain(|fragment| !delete(Some(y), fragment));
+        }
+        if scrollback {
+            for row in &mut self.scrollback {
+                row.images.retain(|fragment| !delete(None, fragment));
+            }
+        }
+        self.dirty = true;
+    }
+
+    /// Registra o link aberto por OSC 8, liberando antes os que nenhuma
+    /// célula usa mais se a tabela tiver crescido
+    pub fn intern_hyperlink(&mut self, link: Hyperlink) -> LinkId {
+        if self.hyperlinks.needs_prune() {
+            self.prune_hyperlinks();
+        }
+        self.hyperlinks.intern(link)
+    }
+
+    /// Libera os links sem referência em células (telas e scrollback) ou cursores
+    fn prune_hyperlinks(&mut self) {
+        let rows = self.cells.iter().chain(&self.inactive_cells).chain(&self.scrollback);
+        let saved = [self.saved_cursor, self.inactive_saved_cursor].into_iter().flatten();
+        let styles = rows
+            .flat_map(|row| row.iter().map(|cell| cell.style))
+            .chain(saved.map(|saved| saved.style))
+            .chain([self.current_style]);
+        let used: HashSet<LinkId> = styles.filter_map(|style| style.hyperlink).collect();
+        self.hyperlinks.retain(&used);
+    }
+
+    /// Hyperlink da célula exibida em (x, y), se houver
+    pub fn hyperlink_at(&self, x: usize, y: usize) -> Option<LinkId> {
+        if y >= self.rows {
+            return None;
+        }
+        self.visible_row(y).get(x)?.style.hyperlink
+    }
+
+    /// Ativa ou desativa um modo
+    pub fn set_mode(&mut self, mode: Mode, enable: bool) {
+        match mode {
+            Mode::Insert => self.modes.insert = enable,
+            Mode::LineFeedNewLine => self.modes.linefeed_newline = enable,
+            Mode::CursorKeys => self.modes.cursor_keys = enable,
+            Mode::Origin => {
+                self.modes.origin = enable;
+                self.goto(0, 0);
+            }
+            Mode::Autowrap => self.modes.autowrap = enable,
+            Mode::ShowCursor => self.modes.show_cursor = enable,
+            Mode::AltScreen if enable => self.enter_alt_screen(false, false),
+            Mode::AltScreen => self.exit_alt_screen(false, false),
+            Mode::AltScreenClear if enable => self.enter_alt_screen(false, false),
+            Mode::AltScreenClear => self.exit_alt_screen(false, true),
+            Mode::AltScreenSaveCursor if enable => self.enter_alt_screen(true, true),
+            Mode::AltScreenSaveCursor => self.exit_alt_screen(true, false),
+        }
+        self.dirty = true;
+    }
+
+    /// Reset completo (RIS)
+    pub fn reset(&mut self) {
+        self.exit_alt_screen(false, false);
+        self.modes = TermModes::default();
+        self.charsets = Charsets::default();
+        self.palette = Palette::new();
+        self.current_style = CellStyle::default();
+        self.saved_cursor = None;
+        self.inactive_saved_cursor = None;
+        self.scroll_top = 0;
+        self.scroll_bottom = self.rows.saturating_sub(1);
+        self.tabs = Self::default_tabs(self.cols);
+        self.zone = Zone::None;
+        self.display_offset = 0;
+        self.selection = None;
+        self.clear();
+        self.prune_hyperlinks();
+    }
+
+    /// Copia um buffer de células para novas dimensões
+    fn resize_cells(cells: &[Row], cols: usize, rows: usize) -> Vec<Row> {
+        let mut new_cells: Vec<Row> = cells.iter().take(rows).cloned().collect();
+        new_cells.resize(rows, Row::new(cols, Cell::default()));
+        for row in &mut new_cells {
+            Self::resize_row(row, cols);
+        }
+        new_cells
+    }
+
+    /// Corta ou completa uma linha até `cols` células, mantendo as marcas semânticas
+    fn resize_row(row: &mut Row, cols: usize) {
+        row.resize(cols, Cell::default());
+        // Caractere largo cortado ao meio pela nova largura
+        if let Some(last) = row.last_mut()
+            && last.width == CellWidth::Wide
+        {
+            *last = Cell::default();
+        }
+    }
+
+    /// Entra na tela alternativa
+    /// `save_cursor` e `clear` combinam os modos 47, 1047 e 1049
+    pub fn enter_alt_screen(&mut self, save_cursor: bool, clear: bool) {
+        if save_cursor {
+            self.save_cursor();
+        }
+        if !self.alt_screen {
+            self.swap_screens();
+            self.alt_screen = true;
+        }
+        if clear {
+            self.fill_screen();
+        }
+        // A tela nova aparece ao vivo, não rolada no histórico da primária
+        self.display_offset = 0;
+        self.dirty = true;
+    }
+
+    /// Volta para a tela primária, preservando seu conteúdo
+    pub fn exit_alt_screen(&mut self, restore_cursor: bool, clear: bool) {
+        if self.alt_screen {
+            if clear {
+                self.fill_screen();
+            }
+            self.swap_screens();
+            self.alt_screen = false;
+        }
+        if restore_cursor {
+            self.restore_cursor();
+        }
+        self.display_offset = 0;
         self.dirty = true;
     }
 
+    /// Troca buffers e cursores salvos entre as telas
+    fn swap_screens(&mut self) {
+        std::mem::swap(&mut self.cells, &mut self.inactive_cells);
+        std::mem::swap(&mut self.saved_cursor, &mut self.inactive_saved_cursor);
+    }
+
+    /// Salva o cursor da tela ativa (DECSC)
+    pub fn save_cursor(&mut self) {
+        self.saved_cursor = Some(SavedCursor {
+            x: self.cursor_x.min(self.cols.saturating_sub(1)),
+            y: self.cursor_y,
+            style: self.current_style,
+            origin: self.modes.origin,
+            pending_wrap: self.cursor_x >= self.cols,
+            charsets: self.charsets,
+        });
+    }
+
+    /// Restaura o cursor da tela ativa (DECRC)
+    /// Sem save anterior, volta para a origem com estilo padrão
+    pub fn restore_cursor(&mut self) {
+        let saved = self.saved_cursor.unwrap_or_default();
+        self.current_style = saved.style;
+        self.modes.origin = saved.origin;
+        self.charsets = saved.charsets;
+        self.move_cursor(saved.x, saved.y);
+        if saved.pending_wrap && self.cursor_x + 1 == self.cols {
+            self.cursor_x = self.cols;
+        }
+    }
+
+    /// Apaga todas as células visíveis sem mover o cursor, com o background atual
+    fn fill_screen(&mut self) {
+        let blank = self.blank_line();
+        for row in &mut self.cells {
+            *row = blank.clone();
+        }
+    }
+
     /// Marca tudo como limpo
     pub fn mark_clean(&mut self) {
         self.dirty = false;
         for row in &mut self.cells {
-            for cell in row {
+            for cell in row.iter_mut() {
                 cell.dirty = false;
             }
         }
     }
+
+    /// Linha `index` contando do início do scrollback até o fim da tela
+    fn line(&self, index: usize) -> &Row {
+        match index.checked_sub(self.scrollback.len()) {
+            Some(y) => &self.cells[y],
+            None => &self.scrollback[index],
+        }
+    }
+
+    /// Total de linhas endereçáveis por `line`
+    fn total_lines(&self) -> usize {
+        self.scrollback.len() + self.rows
+    }
+
+    /// Índice (em `line`) da primeira linha exibida
+    fn display_top(&self) -> usize {
+        self.scrollback.len() - self.display_offset
+    }
+
+    /// Linha exibida na posição `y` da tela, considerando o scroll do histórico
+    pub fn visible_row(&self, y: usize) -> &Row {
+        self.line(self.display_top() + y)
+    }
+
+    /// Linhas do histórico acima da tela exibida (0 = tela ao vivo)
+    pub fn display_offset(&self) -> usize {
+        self.display_offset
+    }
+
+    /// Volta a exibir a tela ao vivo
+    pub fn reset_display(&mut self) {
+        if self.display_offset > 0 {
+            self.display_offset = 0;
+            self.dirty = true;
+        }
+    }
+
+    /// Aplica uma marca de OSC 133 na linha do cursor
+    pub fn semantic_mark(&mut self, mark: SemanticMark) {
+        let y = self.cursor_y.min(self.rows - 1);
+        match mark {
+            SemanticMark::PromptStart => {
+                self.zone = Zone::Prompt;
+                let row = &mut self.cells[y];
+                row.zone = Zone::Prompt;
+                row.prompt = true;
+                row.exit_code = None;
+            }
+            SemanticMark::InputStart => self.zone = Zone::Input,
+            SemanticMark::OutputStart => {
+                self.zone = Zone::Output;
+                // Saída começando no meio da linha fica para a próxima
+                if self.cursor_x == 0 {
+                    self.cells[y].zone = Zone::Output;
+                }
+            }
+            SemanticMark::CommandEnd(exit_code) => {
+                self.zone = Zone::None;
+                // Exit code fica no prompt do comando
+                let cursor_line = self.scrollback.len() + y;
+                if let Some(prompt) = self.prompt_at_or_before(cursor_line) {
+                    self.line_mut(prompt).exit_code = exit_code;
+                }
+            }
+        }
+    }
+
+    fn line_mut(&mut self, index: usize) -> &mut Row {
+        match index.checked_sub(self.scrollback.len()) {
+            Some(y) => &mut self.cells[y],
+            None => &mut self.scrollback[index],
+        }
+    }
+
+    /// Último prompt na linha `index` ou acima
+    fn prompt_at_or_before(&self, index: usize) -> Option<usize> {
+        (0..=index.min(self.total_lines() - 1))
+            .rev()
+            .find(|&i| self.line(i).prompt)
+    }
+
+    /// Rola a exibição até o prompt anterior ou seguinte ao topo da tela
+    pub fn jump_to_prompt(&mut self, forward: bool) {
+        if self.alt_screen {
+            return;
+        }
+
+        let top = self.display_top();
+        let target = if forward {
+            (top + 1..self.total_lines()).find(|&i| self.line(i).prompt)
+        } else {
+            (0..top).rev().find(|&i| self.line(i).prompt)
+        };
+
+        // Sem prompt seguinte, volta à tela ao vivo
+        let offset = match target {
+            Some(line) => self.scrollback.len().saturating_sub(line),
+            None if forward => 0,
+            None => return,
+        };
+        self.display_offset = offset;
+        self.dirty = true;
+    }
+
+    /// Linhas de saída do comando iniciado pelo prompt na linha `prompt`
+    fn command_output(&self, prompt: usize) -> Option<RangeInclusive<usize>> {
+        let end = (prompt + 1..self.total_lines())
+            .find(|&i| self.line(i).prompt)
+            .unwrap_or(self.total_lines());
+
+        let mut output = (prompt..end).filter(|&i| self.line(i).zone == Zone::Output);
+        let first = output.next()?;
+        // Linhas vazias no fim da saída (ex.: a linha do cursor) ficam de fora
+        let last = (first..end)
+            .rev()
+            .find(|&i| self.line(i).zone == Zone::Output && !self.line(i).text().is_empty())?;
+        Some(first..=last)
+    }
+
+    /// Saída do último comando que produziu alguma
+    fn last_output(&self) -> Option<RangeInclusive<usize>> {
+        (0..self.total_lines())
+            .rev()
+            .filter(|&i| self.line(i).prompt)
+            .find_map(|prompt| self.command_output(prompt))
+    }
+
+    /// Seleciona a saída do comando no topo da exibição, ou a do último
+    /// comando quando a tela ao vivo está exibida
+    pub fn select_command_output(&mut self) {
+        let output = if self.display_offset > 0 {
+            self.prompt_at_or_before(self.display_top())
+                .and_then(|prompt| self.command_output(prompt))
+        } else {
+            self.last_output()
+        };
+        if output.is_some() {
+            self.selection = output;
+            self.dirty = true;
+        }
+    }
+
+    /// Texto da saída do último comando
+    pub fn last_output_text(&self) -> Option<String> {
+        self.last_output().map(|range| self.lines_text(range))
+    }
+
+    /// Texto das linhas selecionadas
+    pub fn selection_text(&self) -> Option<String> {
+        self.selection.clone().map(|range| self.lines_text(range))
+    }
+
+    /// Linha exibida `y` está selecionada
+    pub fn is_selected(&self, y: usize) -> bool {
+        self.selection
+            .as_ref()
+            .is_some_and(|range| range.contains(&(self.display_top() + y)))
+    }
+
+    /// Remove a seleção
+    pub fn clear_selection(&mut self) {
+        if self.selection.take().is_some() {
+            self.dirty = true;
+        }
+    }
+
+    fn lines_text(&self, range: RangeInclusive<usize>) -> String {
+        range
+            .map(|i| self.line(i).text())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scrollback_follows_resize() {
+        let mut grid = Grid::new(10, 3);
+        grid.semantic_mark(SemanticMark::PromptStart);
+        for _ in 0..6 {
+            grid.write_char('a');
+            grid.newline();
+        }
+        grid.semantic_mark(SemanticMark::PromptStart);
+
+        grid.resize(20, 3);
+        grid.jump_to_prompt(false);
+        assert!(grid.display_offset() > 0);
+        for y in 0..grid.rows {
+            assert_eq!(grid.visible_row(y).len(), grid.cols);
+            for x in 0..grid.cols {
+                let _ = &grid.visible_row(y)[x];
+            }
+        }
+
+        grid.resize(5, 3);
+        assert!(grid.scrollback.iter().all(|row| row.len() == 5));
+    }
 }
+
@@ -0,0 +1,98 @@
+//! Hyperlinks explícitos (OSC 8)
+//! As células guardam só um `LinkId`; URI e id ficam numa tabela do grid
+
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroU32;
+
+/// Tamanho da tabela a partir do qual o grid procura links sem referência
+const PRUNE_MIN: usize = 1024;
+
+/// Referência a um link na tabela (4 bytes, `Option` sem custo extra)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LinkId(NonZeroU32);
+
+/// Link aberto por `OSC 8 ; params ; URI`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hyperlink {
+    /// Parâmetro `id=`; células com o mesmo id formam um único link
+    pub id: Option<String>,
+    pub uri: String,
+}
+
+/// Tabela de links referenciados pelas células
+#[derive(Debug)]
+pub struct Hyperlinks {
+    /// Entradas por `LinkId`; `None` é uma entrada liberada
+    links: Vec<Option<Hyperlink>>,
+    /// Entradas liberadas, reaproveitadas antes de crescer a tabela
+    free: Vec<LinkId>,
+    /// Id e URI de cada link, para reaproveitar o mesmo `LinkId`
+    by_key: HashMap<(Option<String>, String), LinkId>,
+    /// Entradas em uso que disparam a próxima limpeza
+    prune_at: usize,
+}
+
+impl Hyperlinks {
+    /// Registra um link; id e URI iguais (ou só a URI, sem id) retornam o mesmo `LinkId`
+    pub fn intern(&mut self, link: Hyperlink) -> LinkId {
+        let key = (link.id.clone(), link.uri.clone());
+        if let Some(&existing) = self.by_key.get(&key) {
+            return existing;
+        }
+
+        let id = match self.free.pop() {
+            Some(id) => {
+                self.links[Self::index(id)] = Some(link);
+                id
+            }
+            None => {
+                self.links.push(Some(link));
+                LinkId(NonZeroU32::new(self.links.len() as u32).expect("índice a partir de 1"))
+            }
+        };
+        self.by_key.insert(key, id);
+        id
+    }
+
+    pub fn get(&self, id: LinkId) -> Option<&Hyperlink> {
+        self.links.get(Self::index(id))?.as_ref()
+    }
+
+    /// Entradas em uso
+    pub fn len(&self) -> usize {
+        self.links.len() - self.free.len()
+    }
+
+    /// A tabela cresceu o bastante desde a última limpeza
+    pub fn needs_prune(&self) -> bool {
+        self.len() >= self.prune_at
+    }
+
+    /// Libera os links fora de `used`; os `LinkId` liberados podem ser reaproveitados
+    pub fn retain(&mut self, used: &HashSet<LinkId>) {
+        for (index, slot) in self.links.iter_mut().enumerate() {
+            let id = LinkId(NonZeroU32::new(index as u32 + 1).expect("índice a partir de 1"));
+            if slot.is_some() && !used.contains(&id) {
+                let link = slot.take().expect("entrada em uso");
+                self.by_key.remove(&(link.id, link.uri));
+                self.free.push(id);
+            }
+        }
+        self.prune_at = (self.len() * 2).max(PRUNE_MIN);
+    }
+
+    fn index(id: LinkId) -> usize {
+        id.0.get() as usize - 1
+    }
+}
+
+impl Default for Hyperlinks {
+    fn default() -> Self {
+        Self {
+            links: Vec::new(),
+            free: Vec::new(),
+            by_key: HashMap::new(),
+            prune_at: PRUNE_MIN,
+        }
+    }
+}
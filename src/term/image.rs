@@ -0,0 +1,141 @@
+//! Imagens no grid (sixel e kitty graphics)
+//! Cada linha guarda as faixas das imagens que passam por ela, então as
+//! imagens rolam junto com o texto para o scrollback
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Maior largura/altura aceita, em pixels (cabe no atlas de imagens)
+pub const IMAGE_MAX_SIZE: u32 = 4096;
+
+/// Z-index abaixo do qual a imagem fica sob o background das células (kitty)
+pub const Z_BELOW_BACKGROUND: i32 = i32::MIN / 2;
+
+/// Próximo id de imagem, usado como chave no cache da GPU
+static NEXT_IMAGE_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Imagem decodificada em RGBA8 (sRGB, alpha não pré-multiplicado)
+#[derive(Debug)]
+pub struct Image {
+    pub id: u64,
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32, data: Vec<u8>) -> Self {
+        Self {
+            id: NEXT_IMAGE_ID.fetch_add(1, Ordering::Relaxed),
+            width,
+            height,
+            data,
+        }
+    }
+}
+
+/// Como exibir uma imagem a partir do cursor
+#[derive(Clone, Copy, Debug)]
+pub struct Placement {
+    /// Recorte da imagem (x, y, largura, altura), em pixels da imagem
+    pub source: [u32; 4],
+    /// Tamanho na tela, em pixels
+    pub width: u32,
+    pub height: u32,
+    /// Deslocamento dentro da célula do cursor, em pixels
+    pub offset_x: u32,
+    pub offset_y: u32,
+    pub z: i32,
+    /// Imagem e placement kitty (`i` e `p`, ou id interno para puts sem `p`)
+    pub kitty: Option<(u32, u32)>,
+}
+
+impl Placement {
+    /// Imagem inteira, em tamanho natural, acima do texto
+    pub fn natural(image: &Image) -> Self {
+        Self {
+            source: [0, 0, image.width, image.height],
+            width: image.width,
+            height: image.height,
+            offset_x: 0,
+            offset_y: 0,
+            z: 0,
+            kitty: None,
+        }
+    }
+
+    /// Colunas ocupadas com células de `cell_width` pixels
+    pub fn columns(&self, cell_width: u32) -> usize {
+        (self.offset_x + self.width).div_ceil(cell_width) as usize
+    }
+
+    /// Divide a placement em uma faixa por linha, a partir da coluna `col`
+    pub fn fragments(&self, image: &Arc<Image>, col: usize, cell_height: u32) -> Vec<ImageFragment> {
+        let [source_x, source_y, source_width, source_height] = self.source;
+        let scale = source_height as f32 / self.height.max(1) as f32;
+        let bottom = self.offset_y + self.height;
+
+        (0..bottom.div_ceil(cell_height))
+            .map(|row| {
+                let row_top = row * cell_height;
+                let top = row_top.max(self.offset_y);
+                let height = (row_top + cell_height).min(bottom) - top;
+                ImageFragment {
+                    image: image.clone(),
+                    col,
+                    x: self.offset_x,
+                    y: top - row_top,
+                    width: self.width,
+                    height,
+                    source: [
+                        source_x as f32,
+                        source_y as f32 + (top - self.offset_y) as f32 * scale,
+                        source_width as f32,
+                        height as f32 * scale,
+                    ],
+                    z: self.z,
+                    kitty: self.kitty,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Parte de uma imagem exibida em uma linha
+#[derive(Clone, Debug)]
+pub struct ImageFragment {
+    pub image: Arc<Image>,
+    /// Coluna do canto esquerdo da imagem
+    pub col: usize,
+    /// Retângulo na tela, em pixels, relativo ao canto da célula `col`
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Recorte correspondente da imagem (x, y, largura, altura)
+    pub source: [f32; 4],
+    /// Ordem de composição: negativos ficam sob o texto
+    pub z: i32,
+    /// Imagem e placement kitty (`i` e `p`, ou id interno para puts sem `p`)
+    pub kitty: Option<(u32, u32)>,
+}
+
+impl ImageFragment {
+    /// Colunas ocupadas com células de `cell_width` pixels
+    pub fn columns(&self, cell_width: u32) -> std::ops::Range<usize> {
+        self.col..self.col + (self.x + self.width).div_ceil(cell_width) as usize
+    }
+
+    /// A faixa esconde completamente `other` (área e ordem de composição)
+    /// Só vale para sixel; placements kitty são substituídas explicitamente
+    pub fn covers(&self, other: &Self, cell_width: u32) -> bool {
+        let left = |fragment: &Self| fragment.col as u32 * cell_width + fragment.x;
+        let (mine, theirs) = (left(self), left(other));
+        self.kitty.is_none()
+            && self.z >= other.z
+            && mine <= theirs
+            && mine + self.width >= theirs + other.width
+            && self.y <= other.y
+            && self.y + self.height >= other.y + other.height
+    }
+}
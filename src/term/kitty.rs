@@ -0,0 +1,708 @@
+//! Kitty graphics protocol
+//! `ESC _ G <controle> ; <payload> ESC \`: transmissão direta, por arquivo ou
+//! memória compartilhada, PNG e RGB(A) cru, uploads em partes, placements com
+//! z-index, remoção e respostas
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::CString;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use crate::config::{CELL_HEIGHT, CELL_WIDTH};
+use super::grid::Grid;
+use super::image::{Image, Placement, IMAGE_MAX_SIZE};
+
+/// Maior transmissão aceita, já descomprimida (RGBA do tamanho máximo)
+const TRANSFER_MAX_BYTES: usize = (IMAGE_MAX_SIZE * IMAGE_MAX_SIZE * 4) as usize;
+
+/// Quota das imagens guardadas; as mais antigas sem placement saem primeiro
+const STORAGE_MAX_BYTES: usize = 320 << 20;
+
+/// Ids atribuídos pelo terminal (`I` sem `i`, placements sem `p`) começam aqui
+const AUTO_ID_START: u32 = 1 << 31;
+
+/// Chaves de controle de um comando
+#[derive(Clone, Copy, Debug, Default)]
+struct Command {
+    /// `a` - t(ransmit), T (transmit + put), p(ut), d(elete), q(uery)
+    action: u8,
+    /// `q` - 1 omite OK, 2 omite também os erros
+    quiet: u32,
+    /// `f` - 24 (RGB), 32 (RGBA) ou 100 (PNG)
+    format: u32,
+    /// `t` - d(ireto), f(ile), t(emp file), s(hared memory)
+    medium: u8,
+    /// `o` - z (zlib)
+    compression: u8,
+    /// `s`, `v` - dimensões dos formatos crus
+    width: u32,
+    height: u32,
+    /// `S`, `O` - bytes a ler de arquivo/memória compartilhada
+    size: u32,
+    offset: u32,
+    /// `i`, `I`, `p`
+    id: u32,
+    number: u32,
+    placement: u32,
+    /// `m` - mais partes a seguir
+    more: bool,
+    /// `x`, `y`, `w`, `h` - recorte (na remoção, `x` e `y` são células)
+    source: [u32; 4],
+    /// `X`, `Y` - deslocamento dentro da célula
+    offset_x: u32,
+    offset_y: u32,
+    /// `c`, `r` - tamanho em células
+    columns: u32,
+    rows: u32,
+    /// `z`
+    z: i32,
+    /// `C=1` - não move o cursor
+    keep_cursor: bool,
+    /// `U=1` - placement virtual (unicode placeholders)
+    virtual_placement: bool,
+    /// `d` - alvo da remoção
+    delete: u8,
+}
+
+impl Command {
+    fn parse(control: &[u8]) -> Self {
+        let mut command = Self {
+            action: b't',
+            format: 32,
+            medium: b'd',
+            delete: b'a',
+            ..Self::default()
+        };
+
+        for pair in control.split(|&b| b == b',') {
+            let Some(eq) = pair.iter().position(|&b| b == b'=') else {
+                continue;
+            };
+            let (&[key], value) = (&pair[..eq], &pair[eq + 1..]) else {
+                continue;
+            };
+            let letter = value.first().copied().unwrap_or(0);
+            let number = std::str::from_utf8(value)
+                .ok()
+                .and_then(|value| value.parse::<i64>().ok())
+                .unwrap_or(0);
+            let unsigned = number.clamp(0, u32::MAX as i64) as u32;
+
+            match key {
+                b'a' => command.action = letter,
+                b'q' => command.quiet = unsigned,
+                b'f' => command.format = unsigned,
+                b't' => command.medium = letter,
+                b'o' => command.compression = letter,
+                b's' => command.width = unsigned,
+                b'v' => command.height = unsigned,
+                b'S' => command.size = unsigned,
+                b'O' => command.offset = unsigned,
+                b'i' => command.id = unsigned,
+                b'I' => command.number = unsigned,
+                b'p' => command.placement = unsigned,
+                b'm' => command.more = unsigned == 1,
+                b'x' => command.source[0] = unsigned,
+                b'y' => command.source[1] = unsigned,
+                b'w' => command.source[2] = unsigned,
+                b'h' => command.source[3] = unsigned,
+                b'X' => command.offset_x = unsigned,
+                b'Y' => command.offset_y = unsigned,
+                b'c' => command.columns = unsigned,
+                b'r' => command.rows = unsigned,
+                b'z' => command.z = number.clamp(i32::MIN as i64, i32::MAX as i64) as i32,
+                b'C' => command.keep_cursor = unsigned == 1,
+                b'U' => command.virtual_placement = unsigned == 1,
+                b'd' => command.delete = letter,
+                _ => {}
+            }
+        }
+        command
+    }
+}
+
+/// Upload em partes (`m=1`) aguardando a última
+struct Upload {
+    command: Command,
+    payload: Vec<u8>,
+    /// Payload passou do limite; a transmissão falha no fim
+    overflow: bool,
+}
+
+/// Imagem transmitida, disponível para placements
+struct StoredImage {
+    image: Arc<Image>,
+    /// `I` da transmissão (0 se não houver)
+    number: u32,
+    /// Ordem de chegada, para achar o `I` mais recente e para a quota
+    serial: u64,
+}
+
+/// Imagens transmitidas e upload em andamento
+#[derive(Default)]
+pub struct KittyGraphics {
+    images: HashMap<u32, StoredImage>,
+    upload: Option<Upload>,
+    next_id: u32,
+    /// Última placement interna; cada put sem `p` é uma placement distinta
+    next_placement: u32,
+    serial: u64,
+}
+
+impl KittyGraphics {
+    /// Executa um comando (APC sem o `G`); devolve a resposta para o PTY
+    pub fn command(&mut self, data: &[u8], grid: &mut Grid) -> Option<Vec<u8>> {
+        let (control, payload) = match data.iter().position(|&b| b == b';') {
+            Some(i) => (&data[..i], &data[i + 1..]),
+            None => (data, &[][..]),
+        };
+        let mut command = Command::parse(control);
+        let mut payload = payload.to_vec();
+
+        // Continuação de um upload: das chaves novas só `m` importa
+        if let Some(mut upload) = self.upload.take() {
+            upload.overflow |= upload.payload.len() + payload.len() > TRANSFER_MAX_BYTES / 3 * 4 + 4;
+            if !upload.overflow {
+                upload.payload.extend_from_slice(&payload);
+            }
+            if command.more {
+                self.upload = Some(upload);
+                return None;
+            }
+            if upload.overflow {
+                return Self::reply(&upload.command, upload.command.id, Err("EFBIG:upload too large".into()));
+            }
+            command = upload.command;
+            payload = upload.payload;
+        } else if command.more && matches!(command.action, b't' | b'T' | b'q') {
+            self.upload = Some(Upload {
+                command,
+                payload,
+                overflow: false,
+            });
+            return None;
+        }
+
+        let (id, result) = match command.action {
+            b't' | b'T' | b'q' => match self.transmit(&command, &payload, grid) {
+                Ok(id) => (id, Ok(())),
+                Err(error) => (command.id, Err(error)),
+            },
+            b'p' => (command.id, self.put(&command, grid)),
+            b'd' => {
+                self.delete(&command, grid);
+                return None;
+            }
+            action => {
+                log::debug!("Ação kitty não suportada: {}", action as char);
+                (command.id, Err("EINVAL:unsupported action".into()))
+            }
+        };
+        Self::reply(&command, id, result)
+    }
+
+    /// Resposta `ESC _ G i=..;OK ESC \`, só quando o cliente deu `i` ou `I`
+    fn reply(command: &Command, id: u32, result: Result<(), String>) -> Option<Vec<u8>> {
+        if command.id == 0 && command.number == 0 {
+            return None;
+        }
+        let message = match result {
+            Ok(()) if command.quiet >= 1 => return None,
+            Err(_) if command.quiet >= 2 => return None,
+            Ok(()) => "OK".to_string(),
+            Err(error) => error,
+        };
+
+        let mut keys = Vec::new();
+        if id != 0 {
+            keys.push(format!("i={}", id));
+        }
+        if command.number != 0 {
+            keys.push(format!("I={}", command.number));
+        }
+        if command.placement != 0 {
+            keys.push(format!("p={}", command.placement));
+        }
+        Some(format!("\x1b_G{};{}\x1b\\", keys.join(","), message).into_bytes())
+    }
+
+    /// Carrega e decodifica uma imagem; guarda (exceto `a=q`) e exibe com `a=T`
+    /// Devolve o id usado, atribuído pelo terminal se o cliente só deu `I`
+    fn transmit(&mut self, command: &Command, payload: &[u8], grid: &mut Grid) -> Result<u32, String> {
+        let data = Self::load(command, payload)?;
+        let data = match command.compression {
+            0 => data,
+            b'z' => inflate(&data)?,
+            _ => return Err("EINVAL:unknown compression".into()),
+        };
+        let (width, height, rgba) = match command.format {
+            100 => decode_png(&data)?,
+            24 | 32 => raw_to_rgba(command, data)?,
+            _ => return Err("EINVAL:unknown format".into()),
+        };
+        if width == 0 || height == 0 {
+            return Err("EINVAL:empty image".into());
+        }
+        if width > IMAGE_MAX_SIZE || height > IMAGE_MAX_SIZE {
+            return Err(format!("EFBIG:image larger than {0}x{0}", IMAGE_MAX_SIZE));
+        }
+
+        // Consulta: valida tudo, mas não guarda
+        if command.action == b'q' {
+            return Ok(command.id);
+        }
+
+        let id = match command.id {
+            0 => self.next_free_id(),
+            id => id,
+        };
+        self.serial += 1;
+        self.images.insert(id, StoredImage {
+            image: Arc::new(Image::new(width, height, rgba)),
+            number: command.number,
+            serial: self.serial,
+        });
+        self.enforce_quota(id);
+
+        if command.action == b'T' {
+            self.put(&Command { id, number: 0, ..*command }, grid)?;
+        }
+        Ok(id)
+    }
+
+    /// Bytes da imagem conforme o meio de transmissão (`t`)
+    fn load(command: &Command, payload: &[u8]) -> Result<Vec<u8>, String> {
+        let decoded = BASE64
+            .decode(payload)
+            .map_err(|_| "EINVAL:invalid base64 payload".to_string())?;
+        if command.medium == b'd' {
+            return Ok(decoded);
+        }
+
+        let name = String::from_utf8(decoded).map_err(|_| "EINVAL:invalid path".to_string())?;
+        match command.medium {
+            b'f' => read_file(Path::new(&name), command, false),
+            b't' => read_file(Path::new(&name), command, true),
+            b's' => read_shared_memory(&name, command),
+            _ => Err("EINVAL:unknown transmission medium".into()),
+        }
+    }
+
+    /// Exibe uma imagem guardada no cursor (`a=p`)
+    fn put(&mut self, command: &Command, grid: &mut Grid) -> Result<(), String> {
+        if command.virtual_placement {
+            return Err("EINVAL:unicode placeholders are not supported".into());
+        }
+        let (id, image) = self
+            .find(command)
+            .ok_or_else(|| "ENOENT:image not found".to_string())?;
+
+        // Recorte, limitado à imagem; largura/altura 0 vão até a borda
+        let [x, y, w, h] = command.source;
+        let x = x.min(image.width);
+        let y = y.min(image.height);
+        let w = if w == 0 { image.width - x } else { w.min(image.width - x) };
+        let h = if h == 0 { image.height - y } else { h.min(image.height - y) };
+        if w == 0 || h == 0 {
+            return Err("EINVAL:empty source rectangle".into());
+        }
+
+        // Tamanho na tela: `c`/`r` escalam, mantendo a proporção se só um vier
+        // Limitado a `IMAGE_MAX_SIZE` pixels por lado: cada linha da imagem é
+        // um `index()` do cursor, e um `r` enorme travaria o terminal rolando
+        let (cell_width, cell_height) = (CELL_WIDTH as u32, CELL_HEIGHT as u32);
+        let columns = command.columns.min(IMAGE_MAX_SIZE / cell_width) * cell_width;
+        let rows = command.rows.min(IMAGE_MAX_SIZE / cell_height) * cell_height;
+        let scale = |value: u32, to: u32, from: u32| {
+            (value as u64 * to as u64).div_ceil(from as u64).min(IMAGE_MAX_SIZE as u64) as u32
+        };
+        let (width, height) = match (columns, rows) {
+            (0, 0) => (w, h),
+            (columns, 0) => (columns, scale(h, columns, w)),
+            (0, rows) => (scale(w, rows, h), rows),
+            (columns, rows) => (columns, rows),
+        };
+
+        let placement = Placement {
+            source: [x, y, w, h],
+            width,
+            height,
+            offset_x: command.offset_x.min(cell_width - 1),
+            offset_y: command.offset_y.min(cell_height - 1),
+            z: command.z,
+            kitty: Some((id, self.placement_id(command.placement))),
+        };
+
+        // Mesmo `i` e `p` substitui a placement anterior
+        if command.placement != 0 {
+            grid.delete_images(true, |_, fragment| fragment.kitty == placement.kitty);
+        }
+
+        if command.keep_cursor {
+            grid.place_image(&image, &placement, false);
+        } else {
+            // Cursor na última linha da imagem, na coluna seguinte a ela
+            let col = grid.cursor_x.min(grid.cols - 1);
+            grid.place_image(&image, &placement, true);
+            grid.cursor_x = (col + placement.columns(cell_width)).min(grid.cols);
+        }
+        Ok(())
+    }
+
+    /// Imagem por `i` ou, sem ele, a mais recente com o `I` dado
+    fn find(&self, command: &Command) -> Option<(u32, Arc<Image>)> {
+        let (id, stored) = if command.id != 0 {
+            (command.id, self.images.get(&command.id)?)
+        } else if command.number != 0 {
+            self.images
+                .iter()
+                .filter(|(_, stored)| stored.number == command.number)
+                .max_by_key(|(_, stored)| stored.serial)
+                .map(|(&id, stored)| (id, stored))?
+        } else {
+            return None;
+        };
+        Some((id, stored.image.clone()))
+    }
+
+    /// Remove placements (`a=d`); com `d` maiúsculo, libera também as imagens
+    /// que ficarem sem placements
+    fn delete(&mut self, command: &Command, grid: &mut Grid) {
+        let cell_width = CELL_WIDTH as u32;
+        let [x, y, ..] = command.source;
+        // Posições na remoção são células 1-based
+        let (col, row) = (x.saturating_sub(1) as usize, y.saturating_sub(1) as usize);
+        let (cursor_x, cursor_y) = (grid.cursor_x, grid.cursor_y);
+
+        let target = match command.delete.to_ascii_lowercase() {
+            b'n' => self.find(&Command { id: 0, ..*command }).map(|(id, _)| id),
+            _ => Some(command.id),
+        };
+
+        // Cada linha guarda uma faixa da placement: o seletor escolhe as
+        // placements e todas as faixas delas saem, na tela e no scrollback
+        let mut placements = HashSet::new();
+        for (line, fragment) in grid.images() {
+            let Some((id, placement)) = fragment.kitty else {
+                continue;
+            };
+            let columns = fragment.columns(cell_width);
+            let selected = match command.delete.to_ascii_lowercase() {
+                b'a' => line.is_some(),
+                b'i' | b'n' => {
+                    Some(id) == target && (command.placement == 0 || placement == command.placement)
+                }
+                b'c' => line == Some(cursor_y) && columns.contains(&cursor_x),
+                b'p' => line == Some(row) && columns.contains(&col),
+                b'q' => line == Some(row) && columns.contains(&col) && fragment.z == command.z,
+                b'x' => line.is_some() && columns.contains(&col),
+                b'y' => line == Some(row),
+                b'z' => line.is_some() && fragment.z == command.z,
+                b'r' => (x..=y).contains(&id),
+                _ => false,
+            };
+            if selected {
+                placements.insert((id, placement));
+            }
+        }
+        grid.delete_images(true, |_, fragment| {
+            fragment.kitty.is_some_and(|key| placements.contains(&key))
+        });
+
+        let mut affected: Vec<u32> = placements.iter().map(|&(id, _)| id).collect();
+
+        if command.delete.is_ascii_uppercase() {
+            if let Some(id) = target.filter(|_| matches!(command.delete, b'I' | b'N')) {
+                affected.push(id);
+            }
+            if command.delete == b'R' {
+                affected.extend(self.images.keys().filter(|id| (x..=y).contains(id)));
+            }
+            // Só libera quem não aparece mais em lugar nenhum
+            self.images.retain(|id, stored| {
+                !affected.contains(id) || Arc::strong_count(&stored.image) > 1
+            });
+        }
+    }
+
+    /// `p` do cliente ou, sem ele, uma placement interna nova a partir de `AUTO_ID_START`
+    fn placement_id(&mut self, placement: u32) -> u32 {
+        if placement != 0 {
+            return placement;
+        }
+        self.next_placement = self.next_placement.max(AUTO_ID_START).wrapping_add(1).max(AUTO_ID_START);
+        self.next_placement
+    }
+
+    /// Primeiro id livre a partir de `AUTO_ID_START`
+    fn next_free_id(&mut self) -> u32 {
+        loop {
+            self.next_id = self.next_id.max(AUTO_ID_START).wrapping_add(1).max(AUTO_ID_START);
+            if !self.images.contains_key(&self.next_id) {
+                return self.next_id;
+            }
+        }
+    }
+
+    /// Descarta as imagens mais antigas sem placements até caber na quota
+    fn enforce_quota(&mut self, keep: u32) {
+        loop {
+            let total: usize = self.images.values().map(|stored| stored.image.data.len()).sum();
+            if total <= STORAGE_MAX_BYTES {
+                return;
+            }
+            let oldest = self
+                .images
+                .iter()
+                .filter(|&(&id, stored)| id != keep && Arc::strong_count(&stored.image) == 1)
+                .min_by_key(|(_, stored)| stored.serial)
+                .map(|(&id, _)| id);
+            match oldest {
+                Some(id) => {
+                    self.images.remove(&id);
+                }
+                None => return,
+            }
+        }
+    }
+}
+
+/// Descomprime zlib (`o=z`) até `TRANSFER_MAX_BYTES`
+fn inflate(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut output = Vec::new();
+    flate2::read::ZlibDecoder::new(data)
+        .take(TRANSFER_MAX_BYTES as u64 + 1)
+        .read_to_end(&mut output)
+        .map_err(|error| format!("EINVAL:zlib: {}", error))?;
+    if output.len() > TRANSFER_MAX_BYTES {
+        return Err("EFBIG:decompressed data too large".into());
+    }
+    Ok(output)
+}
+
+/// Decodifica PNG para RGBA8
+fn decode_png(data: &[u8]) -> Result<(u32, u32, Vec<u8>), String> {
+    let mut decoder = png::Decoder::new_with_limits(data, png::Limits { bytes: TRANSFER_MAX_BYTES });
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder
+        .read_info()
+        .map_err(|error| format!("EBADPNG:{}", error))?;
+
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buffer)
+        .map_err(|error| format!("EBADPNG:{}", error))?;
+    buffer.truncate(info.buffer_size());
+
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => buffer,
+        png::ColorType::Rgb => buffer
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buffer
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Grayscale => buffer.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+        png::ColorType::Indexed => return Err("EBADPNG:unexpanded palette".into()),
+    };
+    Ok((info.width, info.height, rgba))
+}
+
+/// Converte RGB (`f=24`) ou RGBA (`f=32`) cru de `s` x `v` pixels para RGBA
+fn raw_to_rgba(command: &Command, mut data: Vec<u8>) -> Result<(u32, u32, Vec<u8>), String> {
+    let (width, height) = (command.width, command.height);
+    if width > IMAGE_MAX_SIZE || height > IMAGE_MAX_SIZE {
+        return Err(format!("EFBIG:image larger than {0}x{0}", IMAGE_MAX_SIZE));
+    }
+    let bytes_per_pixel = if command.format == 24 { 3 } else { 4 };
+    let needed = width as usize * height as usize * bytes_per_pixel;
+    if data.len() < needed {
+        return Err("ENODATA:insufficient image data".into());
+    }
+    data.truncate(needed);
+
+    let rgba = if bytes_per_pixel == 3 {
+        data.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect()
+    } else {
+        data
+    };
+    Ok((width, height, rgba))
+}
+
+/// Lê `S` bytes a partir de `O` (tudo se `S` = 0), até `TRANSFER_MAX_BYTES`
+fn read_range(mut reader: impl Read + Seek, command: &Command) -> Result<Vec<u8>, String> {
+    let limit = match command.size {
+        0 => TRANSFER_MAX_BYTES as u64 + 1,
+        size => size as u64,
+    };
+    let mut data = Vec::new();
+    reader
+        .seek(SeekFrom::Start(command.offset as u64))
+        .and_then(|_| reader.take(limit).read_to_end(&mut data))
+        .map_err(|error| format!("EBADF:{}", error))?;
+    if data.len() > TRANSFER_MAX_BYTES {
+        return Err("EFBIG:file too large".into());
+    }
+    Ok(data)
+}
+
+/// Arquivo regular (`t=f`), ou temporário removido após a leitura (`t=t`)
+fn read_file(path: &Path, command: &Command, temporary: bool) -> Result<Vec<u8>, String> {
+    let path = path
+        .canonicalize()
+        .map_err(|error| format!("EBADF:{}", error))?;
+
+    // Nada de dispositivos nem arquivos virtuais do kernel
+    let virtual_fs = ["/proc", "/sys", "/dev"]
+        .iter()
+        .any(|dir| path.starts_with(dir) && !path.starts_with("/dev/shm"));
+    if virtual_fs {
+        return Err("EBADF:refusing to read from a virtual filesystem".into());
+    }
+
+    // Temporários: só no diretório temporário e com o marcador do protocolo
+    if temporary {
+        let in_temp_dir = [std::env::temp_dir(), "/tmp".into(), "/dev/shm".into()]
+            .iter()
+            .any(|dir| path.starts_with(dir));
+        let marked = path.to_string_lossy().contains("tty-graphics-protocol");
+        if !in_temp_dir || !marked {
+            return Err("EBADF:not a temporary file of this protocol".into());
+        }
+    }
+
+    let file = File::open(&path).map_err(|error| format!("EBADF:{}", error))?;
+    if !file.metadata().is_ok_and(|metadata| metadata.is_file()) {
+        return Err("EBADF:not a regular file".into());
+    }
+    let data = read_range(file, command);
+
+    if temporary && let Err(error) = std::fs::remove_file(&path) {
+        log::warn!("Falha ao remover {}: {}", path.display(), error);
+    }
+    data
+}
+
+/// Objeto de memória compartilhada POSIX (`t=s`), removido após a leitura
+fn read_shared_memory(name: &str, command: &Command) -> Result<Vec<u8>, String> {
+    let name = CString::new(name).map_err(|_| "EINVAL:invalid shared memory name".to_string())?;
+
+    // SAFETY: `name` é uma string C válida; o fd devolvido é fechado abaixo
+    let fd = unsafe { libc::shm_open(name.as_ptr(), libc::O_RDONLY, 0) };
+    if fd < 0 {
+        return Err(format!("EBADF:{}", std::io::Error::last_os_error()));
+    }
+    let data = map_shared_memory(fd, command);
+
+    // SAFETY: `fd` veio de `shm_open` e não é usado depois daqui
+    unsafe {
+        libc::close(fd);
+        libc::shm_unlink(name.as_ptr());
+    }
+    data
+}
+
+/// Copia o trecho pedido de um objeto de memória compartilhada via mmap
+/// (macOS não permite `read` nesses objetos)
+fn map_shared_memory(fd: libc::c_int, command: &Command) -> Result<Vec<u8>, String> {
+    // SAFETY: `stat` é inicializado por `fstat` antes de ser lido
+    let mut stat = unsafe { std::mem::zeroed::<libc::stat>() };
+    if unsafe { libc::fstat(fd, &mut stat) } < 0 {
+        return Err(format!("EBADF:{}", std::io::Error::last_os_error()));
+    }
+
+    let length = stat.st_size.max(0) as usize;
+    let offset = (command.offset as usize).min(length);
+    let size = match command.size {
+        0 => length - offset,
+        size => (size as usize).min(length - offset),
+    };
+    if size > TRANSFER_MAX_BYTES {
+        return Err("EFBIG:shared memory too large".into());
+    }
+    if size == 0 {
+        return Ok(Vec::new());
+    }
+
+    // SAFETY: mapeamento só leitura de `length` bytes de um fd válido,
+    // desfeito antes de retornar; a cópia fica dentro dos limites
+    unsafe {
+        let map = libc::mmap(
+            std::ptr::null_mut(),
+            length,
+            libc::PROT_READ,
+            libc::MAP_SHARED,
+            fd,
+            0,
+        );
+        if map == libc::MAP_FAILED {
+            return Err(format!("EBADF:{}", std::io::Error::last_os_error()));
+        }
+        let data = std::slice::from_raw_parts(map.cast::<u8>().add(offset), size).to_vec();
+        libc::munmap(map, length);
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Transmite e exibe (`a=T`) uma imagem RGBA de 1 pixel por 3 linhas
+    fn put_tall_image(kitty: &mut KittyGraphics, grid: &mut Grid) {
+        let height = CELL_HEIGHT as u32 * 3;
+        let payload = BASE64.encode(vec![255; 4 * height as usize]);
+        let command = format!("a=T,i=7,f=32,s=1,v={},q=2;{}", height, payload);
+        kitty.command(command.as_bytes(), grid);
+    }
+
+    #[test]
+    fn delete_at_cursor_removes_every_row_of_the_placement() {
+        let mut grid = Grid::new(10, 5);
+        let mut kitty = KittyGraphics::default();
+        put_tall_image(&mut kitty, &mut grid);
+        grid.move_cursor(5, 0);
+        put_tall_image(&mut kitty, &mut grid);
+        assert_eq!(grid.images().count(), 6);
+
+        // Só a placement sob o cursor sai, inteira; a outra (também sem `p`) fica
+        grid.move_cursor(0, 0);
+        kitty.command(b"a=d,d=c", &mut grid);
+        assert_eq!(grid.images().count(), 3);
+        assert!(grid.images().all(|(_, fragment)| fragment.col == 5));
+    }
+
+    #[test]
+    fn delete_visible_removes_rows_in_scrollback() {
+        let mut grid = Grid::new(10, 5);
+        let mut kitty = KittyGraphics::default();
+        put_tall_image(&mut kitty, &mut grid);
+        for _ in 0..4 {
+            grid.linefeed();
+        }
+        assert!(grid.images().any(|(line, _)| line.is_none()));
+        assert!(grid.images().any(|(line, _)| line.is_some()));
+
+        kitty.command(b"a=d,d=a", &mut grid);
+        assert_eq!(grid.images().count(), 0);
+    }
+
+    #[test]
+    fn huge_rows_are_clamped() {
+        let mut grid = Grid::new(10, 5);
+        let mut kitty = KittyGraphics::default();
+        let payload = BASE64.encode([255; 4]);
+        let command = format!("a=T,i=1,f=32,s=1,v=1,r=65535,q=2;{}", payload);
+        kitty.command(command.as_bytes(), &mut grid);
+
+        let rows = (IMAGE_MAX_SIZE / CELL_HEIGHT as u32) as usize;
+        assert_eq!(grid.images().count(), rows);
+        assert!(grid.images().all(|(_, fragment)| fragment.width <= IMAGE_MAX_SIZE));
+    }
+}
+
@@ -1,8 +1,21 @@
-/// Módulo do terminal
-/// Contém grid de células e parser ANSI
+//! Módulo do terminal
+//! Contém grid de células e parser ANSI
 
 pub mod grid;
 pub mod ansi;
+pub mod charset;
+pub mod event;
+pub mod hyperlink;
+pub mod image;
+pub mod kitty;
+pub mod modes;
+pub mod palette;
+pub mod row;
+pub mod sixel;
 
-pub use grid::{Grid, Cell, CellStyle};
+pub use grid::{Grid, Attrs, CellWidth, Underline};
 pub use ansi::AnsiParser;
+pub use event::{ClipboardKind, TermEvent};
+pub use hyperlink::LinkId;
+pub use image::Image;
+pub use modes::CursorShape;
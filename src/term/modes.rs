@@ -0,0 +1,117 @@
+//! Modos do terminal
+//! Estado de SM/RM (ANSI) e DECSET/DECRST (DEC privados)
+
+/// Modos configuráveis via `CSI h` / `CSI l`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// IRM (4) - insere em vez de sobrescrever
+    Insert,
+    /// LNM (20) - LF/VT/FF também fazem CR
+    LineFeedNewLine,
+    /// DECCKM (?1) - setas enviam sequências SS3
+    CursorKeys,
+    /// DECOM (?6) - CUP relativo à região de scroll
+    Origin,
+    /// DECAWM (?7) - quebra automática na última coluna
+    Autowrap,
+    /// DECTCEM (?25) - cursor visível
+    ShowCursor,
+    /// ?47 - tela alternativa
+    AltScreen,
+    /// ?1047 - tela alternativa, limpa ao sair
+    AltScreenClear,
+    /// ?1049 - tela alternativa com save/restore do cursor
+    AltScreenSaveCursor,
+}
+
+impl Mode {
+    /// Modo ANSI (`CSI Pm h`)
+    pub fn from_ansi(n: u16) -> Option<Self> {
+        match n {
+            4 => Some(Self::Insert),
+            20 => Some(Self::LineFeedNewLine),
+            _ => None,
+        }
+    }
+
+    /// Modo DEC privado (`CSI ? Pm h`)
+    pub fn from_dec(n: u16) -> Option<Self> {
+        match n {
+            1 => Some(Self::CursorKeys),
+            6 => Some(Self::Origin),
+            7 => Some(Self::Autowrap),
+            25 => Some(Self::ShowCursor),
+            47 => Some(Self::AltScreen),
+            1047 => Some(Self::AltScreenClear),
+            1049 => Some(Self::AltScreenSaveCursor),
+            _ => None,
+        }
+    }
+}
+
+/// Forma do cursor (DECSCUSR)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CursorShape {
+    #[default]
+    Block,
+    Underline,
+    Bar,
+}
+
+/// Forma e blink do cursor, via `CSI Ps SP q`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CursorStyle {
+    pub shape: CursorShape,
+    pub blinking: bool,
+}
+
+impl CursorStyle {
+    /// Estilo a partir do parâmetro de DECSCUSR (0 e 1: block piscante)
+    pub fn from_decscusr(n: u16) -> Option<Self> {
+        let (shape, blinking) = match n {
+            0 | 1 => (CursorShape::Block, true),
+            2 => (CursorShape::Block, false),
+            3 => (CursorShape::Underline, true),
+            4 => (CursorShape::Underline, false),
+            5 => (CursorShape::Bar, true),
+            6 => (CursorShape::Bar, false),
+            _ => return None,
+        };
+        Some(Self { shape, blinking })
+    }
+}
+
+impl Default for CursorStyle {
+    fn default() -> Self {
+        Self {
+            shape: CursorShape::Block,
+            blinking: true,
+        }
+    }
+}
+
+/// Estado dos modos do terminal
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TermModes {
+    pub insert: bool,
+    pub linefeed_newline: bool,
+    pub cursor_keys: bool,
+    pub origin: bool,
+    pub autowrap: bool,
+    pub show_cursor: bool,
+    pub cursor_style: CursorStyle,
+}
+
+impl Default for TermModes {
+    fn default() -> Self {
+        Self {
+            insert: false,
+            linefeed_newline: false,
+            cursor_keys: false,
+            origin: false,
+            autowrap: true,
+            show_cursor: true,
+            cursor_style: CursorStyle::default(),
+        }
+    }
+}
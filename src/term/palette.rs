@@ -0,0 +1,135 @@
+//! Paleta de cores do terminal
+//! Cores indexadas são resolvidas na renderização, então mudanças na paleta
+//! afetam também o texto já impresso
+
+use std::ops::{Index, IndexMut};
+use crate::config::{PALETTE_256, FG_COLOR, BG_COLOR, CURSOR_COLOR};
+
+/// Cor de uma célula
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color {
+    /// Foreground padrão do tema
+    Foreground,
+    /// Background padrão do tema
+    Background,
+    /// Índice na paleta de 256 cores
+    Indexed(u8),
+    /// True color
+    Rgb(u8, u8, u8),
+}
+
+/// Paleta de 256 cores e cores dinâmicas, editáveis em runtime (OSC 4/10/11/12)
+#[derive(Clone, Debug)]
+pub struct Palette {
+    colors: [[f32; 4]; 256],
+    pub foreground: [f32; 4],
+    pub background: [f32; 4],
+    pub cursor: [f32; 4],
+}
+
+impl Palette {
+    pub fn new() -> Self {
+        Self {
+            colors: PALETTE_256,
+            foreground: FG_COLOR,
+            background: BG_COLOR,
+            cursor: CURSOR_COLOR,
+        }
+    }
+
+    /// Restaura um índice ao valor do tema (OSC 104)
+    pub fn reset_index(&mut self, index: u8) {
+        self[index] = PALETTE_256[index as usize];
+    }
+
+    /// Restaura as cores dinâmicas 10, 11 e 12 (OSC 110–112)
+    pub fn reset_foreground(&mut self) {
+        self.foreground = FG_COLOR;
+    }
+
+    pub fn reset_background(&mut self) {
+        self.background = BG_COLOR;
+    }
+
+    pub fn reset_cursor(&mut self) {
+        self.cursor = CURSOR_COLOR;
+    }
+
+    /// Converte uma `Color` em RGBA
+    pub fn resolve(&self, color: Color) -> [f32; 4] {
+        match color {
+            Color::Foreground => self.foreground,
+            Color::Background => self.background,
+            Color::Indexed(index) => self[index],
+            Color::Rgb(r, g, b) => [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 1.0],
+        }
+    }
+}
+
+/// Interpreta uma especificação de cor X11: `rgb:r/g/b` (1–4 dígitos hex por
+/// componente) ou `#rgb`, `#rrggbb`, `#rrrgggbbb`, `#rrrrggggbbbb`
+pub fn parse_color_spec(spec: &[u8]) -> Option<[f32; 4]> {
+    let spec = std::str::from_utf8(spec).ok()?;
+
+    // Componente hex normalizado para 0.0–1.0 pela sua própria precisão
+    let component = |hex: &str| -> Option<f32> {
+        if hex.is_empty() || hex.len() > 4 {
+            return None;
+        }
+        let value = u32::from_str_radix(hex, 16).ok()?;
+        let max = (1u32 << (hex.len() * 4)) - 1;
+        Some(value as f32 / max as f32)
+    };
+
+    let (r, g, b) = if let Some(rgb) = spec.strip_prefix("rgb:") {
+        let mut parts = rgb.split('/');
+        let rgb = (parts.next()?, parts.next()?, parts.next()?);
+        if parts.next().is_some() {
+            return None;
+        }
+        rgb
+    } else if let Some(hex) = spec.strip_prefix('#') {
+        if !hex.is_ascii() || hex.is_empty() || hex.len() % 3 != 0 || hex.len() > 12 {
+            return None;
+        }
+        let n = hex.len() / 3;
+        (&hex[..n], &hex[n..2 * n], &hex[2 * n..])
+    } else {
+        return None;
+    };
+
+    Some([component(r)?, component(g)?, component(b)?, 1.0])
+}
+
+/// Formata uma cor como `rgb:rrrr/gggg/bbbb` para respostas de consulta
+pub fn format_color_spec(color: [f32; 4]) -> String {
+    let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u16 * 257;
+    format!(
+        "rgb:{:04x}/{:04x}/{:04x}",
+        channel(color[0]),
+        channel(color[1]),
+        channel(color[2]),
+    )
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Leitura de um índice da paleta
+impl Index<u8> for Palette {
+    type Output = [f32; 4];
+
+    fn index(&self, index: u8) -> &Self::Output {
+        &self.colors[index as usize]
+    }
+}
+
+/// Escrita de um índice da paleta em runtime
+impl IndexMut<u8> for Palette {
+    fn index_mut(&mut self, index: u8) -> &mut Self::Output {
+        &mut self.colors[index as usize]
+    }
+}
@@ -0,0 +1,92 @@
+//! Linha do grid
+//! Células mais as marcas semânticas de OSC 133 e as faixas de imagem, que
+//! acompanham a linha quando ela rola para o scrollback
+
+use std::ops::{Deref, DerefMut};
+use super::grid::{Cell, CellWidth};
+use super::image::ImageFragment;
+
+/// Zona semântica de shell integration (OSC 133)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Zone {
+    #[default]
+    None,
+    /// Prompt (A até B)
+    Prompt,
+    /// Comando digitado (B até C)
+    Input,
+    /// Saída do comando (C até D)
+    Output,
+}
+
+/// Marca recebida via `OSC 133 ; <marca>`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SemanticMark {
+    /// `A` - início do prompt
+    PromptStart,
+    /// `B` - fim do prompt, início da entrada
+    InputStart,
+    /// `C` - início da saída
+    OutputStart,
+    /// `D [; exit]` - fim do comando
+    CommandEnd(Option<i32>),
+}
+
+/// Linha de células com metadados semânticos
+#[derive(Clone, Debug)]
+pub struct Row {
+    cells: Vec<Cell>,
+    /// Zona em que a linha começa
+    pub zone: Zone,
+    /// Um prompt começa nesta linha (A)
+    pub prompt: bool,
+    /// Exit code do comando deste prompt (D)
+    pub exit_code: Option<i32>,
+    /// Faixas de imagens desenhadas sobre a linha, em ordem de chegada
+    pub images: Vec<ImageFragment>,
+}
+
+impl Row {
+    /// Linha com `cols` cópias de `cell`
+    pub fn new(cols: usize, cell: Cell) -> Self {
+        Self {
+            cells: vec![cell; cols],
+            zone: Zone::None,
+            prompt: false,
+            exit_code: None,
+            images: Vec::new(),
+        }
+    }
+
+    /// Remove as marcas semânticas
+    pub fn clear_marks(&mut self) {
+        self.zone = Zone::None;
+        self.prompt = false;
+        self.exit_code = None;
+    }
+
+    /// Texto da linha sem espaços à direita
+    pub fn text(&self) -> String {
+        let mut text = String::with_capacity(self.cells.len());
+        for cell in self.cells.iter().filter(|cell| cell.width != CellWidth::Spacer) {
+            text.push(cell.c);
+            text.extend(cell.zerowidth.iter().flatten());
+        }
+        text.truncate(text.trim_end().len());
+        text
+    }
+}
+
+impl Deref for Row {
+    type Target = Vec<Cell>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.cells
+    }
+}
+
+impl DerefMut for Row {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.cells
+    }
+}
@@ -0,0 +1,371 @@
+//! Decodificador de sixel
+//! Recebe o payload de `DCS P1 ; P2 ; P3 q ... ST` byte a byte e monta uma
+//! imagem RGBA
+
+use super::image::IMAGE_MAX_SIZE;
+
+/// Maior largura/altura aceita, em pixels
+pub const SIXEL_MAX_SIZE: usize = IMAGE_MAX_SIZE as usize;
+
+/// Número de registradores de cor
+pub const SIXEL_REGISTERS: usize = 256;
+
+/// Cores iniciais do VT340, em percentuais RGB
+const VT340_COLORS: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (20, 20, 80),
+    (80, 13, 13),
+    (20, 80, 20),
+    (80, 20, 80),
+    (20, 80, 80),
+    (80, 80, 20),
+    (53, 53, 53),
+    (26, 26, 26),
+    (33, 33, 60),
+    (60, 26, 26),
+    (33, 60, 33),
+    (60, 33, 60),
+    (33, 60, 60),
+    (60, 60, 33),
+    (80, 80, 80),
+];
+
+/// Comando com parâmetros numéricos em andamento
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Command {
+    /// Dados sixel
+    None,
+    /// `!Pn` - repetição
+    Repeat,
+    /// `#Pc[;Pu;Px;Py;Pz]` - seleção/definição de cor
+    Color,
+    /// `"Pan;Pad;Ph;Pv` - atributos de raster
+    Raster,
+}
+
+/// Estado de decodificação de uma imagem sixel
+pub struct SixelDecoder {
+    command: Command,
+    params: Vec<u32>,
+    /// Contagem do último `!Pn`, aplicada ao próximo sixel
+    repeat: Option<usize>,
+    registers: [[u8; 4]; SIXEL_REGISTERS],
+    color: usize,
+    /// Pixels sem cor ficam transparentes (P2 = 1) ou com o background
+    transparent: bool,
+    /// Altura de cada pixel sixel em pixels de tela (Pan / Pad)
+    aspect: usize,
+    x: usize,
+    /// Topo da banda de 6 pixels atual
+    y: usize,
+    /// Buffer RGBA de `capacity_width` x `capacity_height`
+    pixels: Vec<u8>,
+    capacity_width: usize,
+    capacity_height: usize,
+    /// Área efetivamente pintada ou declarada via raster
+    width: usize,
+    height: usize,
+}
+
+impl SixelDecoder {
+    /// Novo decodificador com os parâmetros do DCS
+    pub fn new(params: &[u16]) -> Self {
+        let mut registers = [[0, 0, 0, 255]; SIXEL_REGISTERS];
+        for (register, &(r, g, b)) in registers.iter_mut().zip(&VT340_COLORS) {
+            *register = [percent(r as u32), percent(g as u32), percent(b as u32), 255];
+        }
+
+        Self {
+            command: Command::None,
+            params: Vec::with_capacity(5),
+            repeat: None,
+            registers,
+            color: 0,
+            transparent: params.get(1) == Some(&1),
+            aspect: 1,
+            x: 0,
+            y: 0,
+            pixels: Vec::new(),
+            capacity_width: 0,
+            capacity_height: 0,
+            width: 0,
+            height: 0,
+        }
+    }
+
+    /// Alimenta um byte do payload
+    pub fn feed(&mut self, byte: u8) {
+        match byte {
+            b'0'..=b'9' if self.command != Command::None => {
+                let digit = (byte - b'0') as u32;
+                match self.params.last_mut() {
+                    Some(param) => *param = param.saturating_mul(10).saturating_add(digit),
+                    None => self.params.push(digit),
+                }
+            }
+            b';' if self.command != Command::None => {
+                if self.params.is_empty() {
+                    self.params.push(0);
+                }
+                self.params.push(0);
+            }
+            _ => {
+                self.finish_command();
+                match byte {
+                    b'?'..=b'~' => {
+                        let count = self.repeat.take().unwrap_or(1);
+                        self.sixel(byte - b'?', count);
+                    }
+                    b'!' => self.command = Command::Repeat,
+                    b'#' => self.command = Command::Color,
+                    b'"' => self.command = Command::Raster,
+                    // Graphics carriage return
+                    b'$' => self.x = 0,
+                    // Graphics new line
+                    b'-' => {
+                        self.x = 0;
+                        self.y += 6 * self.aspect;
+                    }
+                    _ => {} // Espaços, quebras de linha e lixo são ignorados
+                }
+            }
+        }
+    }
+
+    /// Aplica o comando cujos parâmetros terminaram
+    fn finish_command(&mut self) {
+        let command = std::mem::replace(&mut self.command, Command::None);
+        let params = std::mem::take(&mut self.params);
+        let param = |i: usize| params.get(i).copied().unwrap_or(0);
+
+        match command {
+            Command::None => {}
+            Command::Repeat => self.repeat = Some(param(0).max(1) as usize),
+            Command::Color => {
+                let index = param(0) as usize % SIXEL_REGISTERS;
+                if params.len() >= 5 {
+                    let rgb = match param(1) {
+                        1 => hls_to_rgb(param(2), param(3), param(4)),
+                        2 => [percent(param(2)), percent(param(3)), percent(param(4))],
+                        _ => return,
+                    };
+                    self.registers[index] = [rgb[0], rgb[1], rgb[2], 255];
+                }
+                self.color = index;
+            }
+            Command::Raster => {
+                let (pan, pad) = (param(0), param(1));
+                if pan > 0 && pad > 0 {
+                    self.aspect = (pan as f32 / pad as f32).round().clamp(1.0, 10.0) as usize;
+                }
+                // Tamanho declarado define a área de fundo da imagem
+                let width = (param(2) as usize).min(SIXEL_MAX_SIZE);
+                let height = (param(3) as usize).min(SIXEL_MAX_SIZE);
+                self.reserve(width, height);
+                self.width = self.width.max(width);
+                self.height = self.height.max(height);
+            }
+        }
+    }
+
+    /// Pinta `count` colunas com os 6 bits de `bits`
+    fn sixel(&mut self, bits: u8, count: usize) {
+        let count = count.min(SIXEL_MAX_SIZE.saturating_sub(self.x));
+        let band_height = 6 * self.aspect;
+        if count == 0 || self.y + band_height > SIXEL_MAX_SIZE {
+            self.x += count;
+            return;
+        }
+
+        self.reserve(self.x + count, self.y + band_height);
+        self.width = self.width.max(self.x + count);
+
+        let color = self.registers[self.color];
+        for bit in 0..6 {
+            if bits & (1 << bit) == 0 {
+                continue;
+            }
+            let top = self.y + bit * self.aspect;
+            for y in top..top + self.aspect {
+                let start = (y * self.capacity_width + self.x) * 4;
+                for pixel in self.pixels[start..start + count * 4].chunks_exact_mut(4) {
+                    pixel.copy_from_slice(&color);
+                }
+            }
+            self.height = self.height.max(top + self.aspect);
+        }
+        self.x += count;
+    }
+
+    /// Garante espaço para `width` x `height` pixels, dobrando a dimensão que faltar
+    fn reserve(&mut self, width: usize, height: usize) {
+        if width <= self.capacity_width && height <= self.capacity_height {
+            return;
+        }
+        let grow = |needed: usize, current: usize| {
+            if needed > current {
+                needed.max(current * 2).min(SIXEL_MAX_SIZE)
+            } else {
+                current
+            }
+        };
+        let new_width = grow(width, self.capacity_width);
+        let new_height = grow(height, self.capacity_height);
+
+        let mut pixels = vec![0u8; new_width * new_height * 4];
+        for y in 0..self.capacity_height {
+            let src = y * self.capacity_width * 4;
+            let dst = y * new_width * 4;
+            pixels[dst..dst + self.capacity_width * 4]
+                .copy_from_slice(&self.pixels[src..src + self.capacity_width * 4]);
+        }
+        self.pixels = pixels;
+        self.capacity_width = new_width;
+        self.capacity_height = new_height;
+    }
+
+    /// Finaliza a imagem: (largura, altura, RGBA); `None` se vazia
+    /// Pixels não pintados recebem `background`, a menos que P2 = 1
+    pub fn finish(mut self, background: [f32; 4]) -> Option<(u32, u32, Vec<u8>)> {
+        self.finish_command();
+        if self.width == 0 || self.height == 0 {
+            return None;
+        }
+
+        let background = background.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+        let mut data = Vec::with_capacity(self.width * self.height * 4);
+        for y in 0..self.height {
+            let start = y * self.capacity_width * 4;
+            for pixel in self.pixels[start..start + self.width * 4].chunks_exact(4) {
+                if pixel[3] == 0 && !self.transparent {
+                    data.extend_from_slice(&background);
+                } else {
+                    data.extend_from_slice(pixel);
+                }
+            }
+        }
+        Some((self.width as u32, self.height as u32, data))
+    }
+}
+
+/// Percentual 0–100 para 0–255
+fn percent(value: u32) -> u8 {
+    (value.min(100) * 255 / 100) as u8
+}
+
+/// HLS do DEC (matiz 0 = azul, 120 = vermelho, 240 = verde) para RGB
+fn hls_to_rgb(hue: u32, lightness: u32, saturation: u32) -> [u8; 3] {
+    let hue = ((hue % 360 + 240) % 360) as f32;
+    let l = lightness.min(100) as f32 / 100.0;
+    let s = saturation.min(100) as f32 / 100.0;
+
+    let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    let x = c * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let m = l - c / 2.0;
+    let (r, g, b) = match hue as u32 / 60 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    [r, g, b].map(|v| ((v + m) * 255.0).round() as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BACKGROUND: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+
+    /// Decodifica `data` com P2 = 0 (pixels vazios com o background)
+    fn decode(data: &[u8]) -> Option<(u32, u32, Vec<u8>)> {
+        let mut decoder = SixelDecoder::new(&[0, 0]);
+        for &byte in data {
+            decoder.feed(byte);
+        }
+        decoder.finish(BACKGROUND)
+    }
+
+    fn pixel(image: &(u32, u32, Vec<u8>), x: u32, y: u32) -> [u8; 4] {
+        let start = ((y * image.0 + x) * 4) as usize;
+        image.2[start..start + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn repeat_paints_columns() {
+        let image = decode(b"#1!5~").unwrap();
+        assert_eq!((image.0, image.1), (5, 6));
+        // Registrador 1 do VT340: 20% 20% 80%
+        for x in 0..5 {
+            assert_eq!(pixel(&image, x, 5), [51, 51, 204, 255]);
+        }
+    }
+
+    #[test]
+    fn color_registers_rgb_and_hls() {
+        let image = decode(b"#3;2;100;0;0~#4;1;240;50;100~#3~").unwrap();
+        assert_eq!(pixel(&image, 0, 0), [255, 0, 0, 255]);
+        // HLS do DEC: 240 é verde
+        assert_eq!(pixel(&image, 1, 0), [0, 255, 0, 255]);
+        // Selecionar sem definir mantém a cor do registrador
+        assert_eq!(pixel(&image, 2, 0), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn raster_attributes_size_the_image() {
+        let image = decode(b"\"1;1;10;12#1@").unwrap();
+        assert_eq!((image.0, image.1), (10, 12));
+        assert_eq!(pixel(&image, 0, 0), [51, 51, 204, 255]);
+        assert_eq!(pixel(&image, 9, 11), [0, 0, 255, 255]);
+
+        // Aspecto 2:1 dobra a altura de cada sixel
+        let image = decode(b"\"2;1#1~").unwrap();
+        assert_eq!((image.0, image.1), (1, 12));
+    }
+
+    #[test]
+    fn carriage_return_and_newline() {
+        let image = decode(b"#1~$#2@-#1~").unwrap();
+        assert_eq!((image.0, image.1), (1, 12));
+        // `$` volta à coluna 0 da mesma banda e pinta por cima só o bit 0
+        assert_eq!(pixel(&image, 0, 0)[..3], percent_rgb(VT340_COLORS[2]));
+        assert_eq!(pixel(&image, 0, 1)[..3], percent_rgb(VT340_COLORS[1]));
+        // `-` desce uma banda de 6 pixels
+        assert_eq!(pixel(&image, 0, 6)[..3], percent_rgb(VT340_COLORS[1]));
+    }
+
+    #[test]
+    fn transparent_background() {
+        let mut decoder = SixelDecoder::new(&[0, 1]);
+        for &byte in b"\"1;1;2;6#1@" {
+            decoder.feed(byte);
+        }
+        let image = decoder.finish(BACKGROUND).unwrap();
+        assert_eq!(pixel(&image, 1, 0)[3], 0);
+    }
+
+    #[test]
+    fn truncated_or_invalid_streams() {
+        assert!(decode(b"").is_none());
+        assert!(decode(b"#1;2;").is_none());
+        assert!(decode(b"!").is_none());
+
+        let garbage: Vec<u8> = (0..=255).collect();
+        let _ = decode(&garbage);
+        let _ = decode(b"#999999999;9;999;999;999!4294967295~\"99999;0;99999;99999");
+
+        // Limites de tamanho são respeitados
+        let image = decode(b"!99999~").unwrap();
+        assert_eq!(image.0 as usize, SIXEL_MAX_SIZE);
+        let mut tall = b"#1".to_vec();
+        tall.extend(b"~-".repeat(SIXEL_MAX_SIZE / 6 + 10));
+        let image = decode(&tall).unwrap();
+        assert!(image.1 as usize <= SIXEL_MAX_SIZE);
+    }
+
+    fn percent_rgb((r, g, b): (u8, u8, u8)) -> [u8; 3] {
+        [percent(r as u32), percent(g as u32), percent(b as u32)]
+    }
+}